@@ -1,19 +1,24 @@
 #![allow(clippy::eval_order_dependence)]
 #![allow(dead_code)]
 
+use anyhow::Context;
 use macroquad::{
     audio::{load_sound, Sound},
     miniquad::*,
     prelude::*,
 };
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::utils::tilemap::{MapFormat, Tilemap, Tileset, TilesetSource};
 
 pub struct Assets {
     pub textures: Textures,
     pub sounds: Sounds,
     pub shaders: Shaders,
+    pub maps: Maps,
 }
 
 impl Assets {
@@ -22,6 +27,7 @@ impl Assets {
             textures: Textures::init().await,
             sounds: Sounds::init().await,
             shaders: Shaders::init().await,
+            maps: Maps::init().await,
         }
     }
 }
@@ -41,6 +47,43 @@ impl Textures {
             billboard_patch9: texture("ui/billboard_patch9").await,
         }
     }
+
+    pub fn get(&self, key: TextureKey) -> Texture2D {
+        match key {
+            TextureKey::FontSmall => self.fonts.small,
+            TextureKey::FontMedium => self.fonts.medium,
+            TextureKey::TitleBanner => self.title_banner,
+            TextureKey::BillboardPatch9 => self.billboard_patch9,
+        }
+    }
+
+    /// Which of our textures this is, if it's one of them.
+    pub fn key_of(&self, texture: Texture2D) -> Option<TextureKey> {
+        TextureKey::ALL
+            .iter()
+            .copied()
+            .find(|&key| self.get(key) == texture)
+    }
+}
+
+/// Names for each texture, so things holding textures can be saved and loaded again.
+///
+/// Add a variant here for every texture you add.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextureKey {
+    FontSmall,
+    FontMedium,
+    TitleBanner,
+    BillboardPatch9,
+}
+
+impl TextureKey {
+    pub const ALL: [TextureKey; 4] = [
+        TextureKey::FontSmall,
+        TextureKey::FontMedium,
+        TextureKey::TitleBanner,
+        TextureKey::BillboardPatch9,
+    ];
 }
 
 pub struct Fonts {
@@ -77,6 +120,23 @@ impl Shaders {
     }
 }
 
+/// Tiled maps from `assets/maps`.
+///
+/// Add a field here for every map, and load it with `tilemap("level1.tmx")`.
+/// A map that couldn't be loaded holds the error instead, so a broken map
+/// only stops the modes that use it and not the whole game.
+pub struct Maps {
+    pub example: anyhow::Result<Tilemap>,
+}
+
+impl Maps {
+    async fn init() -> Self {
+        Self {
+            example: tilemap("example.tmx").await,
+        }
+    }
+}
+
 /// Path to the assets root
 static ASSETS_ROOT: Lazy<PathBuf> = Lazy::new(|| {
     if cfg!(target_arch = "wasm32") {
@@ -90,19 +150,52 @@ static ASSETS_ROOT: Lazy<PathBuf> = Lazy::new(|| {
 
 async fn texture(path: &str) -> Texture2D {
     let with_extension = path.to_owned() + ".png";
-    let tex = load_texture(
-        ASSETS_ROOT
-            .join("textures")
-            .join(with_extension)
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
+    texture_at(&ASSETS_ROOT.join("textures").join(with_extension)).await
+}
+
+async fn texture_at(path: &Path) -> Texture2D {
+    let tex = load_texture(path.to_string_lossy().as_ref()).await.unwrap();
     tex.set_filter(FilterMode::Nearest);
     tex
 }
 
+/// Load a Tiled map and all its tilesets and their images.
+/// The format is picked from the extension.
+async fn tilemap(path: &str) -> anyhow::Result<Tilemap> {
+    let full_path = ASSETS_ROOT.join("maps").join(path);
+    let format =
+        MapFormat::of(path).with_context(|| format!("map {} should be .tmx or .json", path))?;
+    let text = load_string(full_path.to_string_lossy().as_ref()).await?;
+    let (mut map, sources) =
+        Tilemap::parse(&text, format).with_context(|| format!("couldn't read map {}", path))?;
+
+    // Images are relative to the file the tileset is in
+    let map_dir = full_path.parent().unwrap();
+    for source in sources {
+        let (mut tileset, dir) = match source {
+            TilesetSource::Embedded(tileset) => (tileset, map_dir.to_owned()),
+            TilesetSource::External { first_gid, path } => {
+                let tileset_path = map_dir.join(&path);
+                let format = MapFormat::of(&path)
+                    .with_context(|| format!("tileset {} should be .tsx or .json", path))?;
+                let text = load_string(tileset_path.to_string_lossy().as_ref()).await?;
+                let tileset = Tileset::parse(&text, format, first_gid)
+                    .with_context(|| format!("couldn't read tileset {}", path))?;
+                (tileset, tileset_path.parent().unwrap().to_owned())
+            }
+        };
+        let image = dir.join(&tileset.image);
+        let texture = load_texture(image.to_string_lossy().as_ref())
+            .await
+            .with_context(|| format!("couldn't load the image for tileset {}", tileset.name))?;
+        texture.set_filter(FilterMode::Nearest);
+        tileset.texture = Some(texture);
+        map.tilesets.push(tileset);
+    }
+    map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+    Ok(map)
+}
+
 async fn sound(path: &str) -> Sound {
     let with_extension = path.to_owned() + ".ogg";
     load_sound(
@@ -1,35 +1,110 @@
 use crate::{
     assets::Assets,
-    controls::InputSubscriber,
+    controls::{Control, InputContext, InputSubscriber},
     modes::{DispatchDrawer, DispatchMode},
+    resources::Resources,
+    transition_fx::{TransitionEffect, TransitionFx},
 };
 use enum_dispatch::enum_dispatch;
 
+use std::any::Any;
+
 /// Things the engine can update and draw
 #[enum_dispatch]
 pub trait Gamemode {
     /// Update the state.
     ///
+    /// `resources` holds the game-wide state shared between modes.
+    ///
     /// Return how to swap to another state if need be.
     fn update(
         &mut self,
         controls: &InputSubscriber,
         frame_info: FrameInfo,
         assets: &Assets,
+        resources: &mut Resources,
     ) -> Transition;
 
     /// Gather information about how to draw this state.
     fn get_draw_info(&mut self) -> DispatchDrawer;
 
+    /// Called once when this mode is put on the stack, before it's first updated.
+    ///
+    /// See `Transition::apply` for the order all the lifecycle hooks run in.
+    #[allow(unused_variables)]
+    fn on_enter(&mut self, assets: &Assets) {}
+
+    /// Called when another mode is pushed on top of this one.
+    #[allow(unused_variables)]
+    fn on_pause(&mut self, assets: &Assets) {}
+
     /// When a `Transition` finishes and things are popped off to reveal this gamemode,
     /// this function is called.
+    ///
+    /// `result` is whatever the mode that was on top of this one handed back with
+    /// `Transition::PopWith`, if anything.
     #[allow(unused_variables)]
-    fn on_resume(&mut self, assets: &Assets) {}
+    fn on_resume(&mut self, result: ModeResult, assets: &Assets) {}
+
+    /// Called right before this mode is taken off the stack for good.
+    #[allow(unused_variables)]
+    fn on_exit(&mut self, assets: &Assets) {}
+
+    /// The set of bindings this mode reads its controls through.
+    fn input_context(&self) -> InputContext {
+        InputContext::default()
+    }
+
+    /// Does this mode let input through to the mode underneath it?
+    ///
+    /// If so, the mode underneath is updated right after this one, and sees the input
+    /// through its own context. (Any `Transition` it returns is ignored, though; only
+    /// the top mode gets to change the stack.)
+    ///
+    /// This covers the axes, mouse wheel and typing, plus every control
+    /// unless `passes_control` says otherwise.
+    fn passes_input(&self) -> bool {
+        false
+    }
+
+    /// Does this mode let this control through to the mode underneath it?
+    ///
+    /// Override this to consume some actions and pass the rest down, like a dialog box
+    /// that eats `Back` but lets the game underneath still see everything else.
+    /// If any control gets through, the mode underneath is updated as if by `passes_input`.
+    #[allow(unused_variables)]
+    fn passes_control(&self, control: Control) -> bool {
+        self.passes_input()
+    }
+
+    /// Can you see the modes underneath this one?
+    ///
+    /// If so, they're drawn first and this is drawn on top of them.
+    /// Pause menus and dialog boxes want this.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    /// Does this mode stop the modes underneath it from updating?
+    ///
+    /// If not, they keep updating, but with all their input masked out
+    /// (unless this passes input too).
+    fn blocks_updates(&self) -> bool {
+        true
+    }
+
+    /// Does this mode stop the clocks of the modes underneath it?
+    ///
+    /// If so, the ones that still update see time standing still.
+    /// (Modes that aren't updated at all never have their clocks move anyways.)
+    fn pauses_below(&self) -> bool {
+        false
+    }
 }
 
 /// Data on how to draw a state
 #[enum_dispatch]
-pub trait GamemodeDrawer: Send {
+pub trait GamemodeDrawer: Send + Sync {
     fn draw(&self, assets: &Assets, frame_info: FrameInfo);
 }
 
@@ -37,7 +112,15 @@ pub trait GamemodeDrawer: Send {
 #[derive(Copy, Clone)]
 pub struct FrameInfo {
     /// Time the previous frame took in seconds.
+    /// For Gamemodes this is game time on the mode's own clock, so it's zero while paused;
+    /// for GamemodeDrawers this is real time.
     pub dt: f32,
+    /// Seconds of game time. Use this for animations and timers, not `get_time`.
+    ///
+    /// For Gamemodes this is the mode's own clock, which starts at zero when it's pushed
+    /// and only runs while it's updated and not paused.
+    /// For GamemodeDrawers this is the engine's `GameClock`.
+    pub time: f64,
     /// Number of frames that have happened since the program started.
     /// For Gamemodes this is update frames; for GamemodeDrawers this is draw frames.
     // at 2^64 frames, this will run out about when the sun dies!
@@ -45,6 +128,7 @@ pub struct FrameInfo {
     // how exciting.
     pub frames_ran: u64,
 }
+
 /// Ways modes can transition
 #[allow(dead_code)]
 pub enum Transition {
@@ -56,45 +140,127 @@ pub enum Transition {
     Push(DispatchMode),
     /// Pop the top mode off the stack
     Pop,
+    /// Pop the top mode off the stack and hand this to the one underneath's `on_resume`
+    PopWith(ModeResult),
     /// The most customizable: pop N entries off the stack, then push some new ones.
     /// The last entry in the vec will become the top of the stack.
     PopNAndPush(usize, Vec<DispatchMode>),
+    /// Do the inner transition, animating it with an effect.
+    /// Use `Transition::with_effect` to make these.
+    WithEffect(TransitionFx, Box<Transition>),
 }
 
 impl Transition {
-    /// Apply the transition
-    pub fn apply(self, stack: &mut Vec<DispatchMode>, assets: &Assets) {
-        let reveal = !matches!(&self, &Transition::None);
-        match self {
-            Transition::None => {}
-            Transition::Swap(new) => {
-                if !stack.is_empty() {
-                    stack.pop();
-                }
-                stack.push(new);
-            }
-            Transition::Push(new) => {
-                stack.push(new);
+    /// Pop the top mode off, handing this value back to the mode underneath.
+    pub fn pop_with<T: Any>(value: T) -> Transition {
+        Transition::PopWith(ModeResult::new(value))
+    }
+
+    /// Animate this transition with an effect lasting this many seconds.
+    pub fn with_effect(self, effect: TransitionEffect, secs: f32) -> Transition {
+        Transition::WithEffect(TransitionFx::new(effect, secs), Box::new(self))
+    }
+
+    /// Apply the transition.
+    ///
+    /// Every variant boils down to popping some modes and then pushing some modes.
+    /// The lifecycle hooks run in this order:
+    ///
+    /// 1. Each popped mode gets `on_exit`, from the top down.
+    /// 2. If any modes are pushed, then for each one from the bottom up:
+    ///    the mode it's about to cover gets `on_pause` (unless it was already covered before
+    ///    this transition), then the new mode gets `on_enter`.
+    /// 3. If modes were popped but none were pushed, the revealed top mode gets `on_resume`,
+    ///    along with the result if this is `PopWith`.
+    ///
+    /// So every mode goes through `on_enter`, then any number of `on_pause`/`on_resume` pairs,
+    /// then `on_exit` (which can come while it's paused).
+    /// The stack is never popped empty; `Pop` on a stack of one mode does nothing.
+    ///
+    /// This ignores any effects; `ModeStack` is what plays them.
+    ///
+    /// Returns how many modes were popped.
+    pub fn apply(self, stack: &mut Vec<DispatchMode>, assets: &Assets) -> usize {
+        let (pop_count, news, result) = match self {
+            Transition::None => return 0,
+            Transition::WithEffect(_, inner) => return inner.apply(stack, assets),
+            Transition::Swap(new) => (1, vec![new], ModeResult::none()),
+            Transition::Push(new) => (0, vec![new], ModeResult::none()),
+            Transition::Pop => (1, Vec::new(), ModeResult::none()),
+            Transition::PopWith(result) => (1, Vec::new(), result),
+            Transition::PopNAndPush(count, news) => (count, news, ModeResult::none()),
+        };
+
+        // If we're not pushing anything, we pop down to at least one state
+        // this would be very bad otherwise
+        let lower_limit = if news.is_empty() { 1 } else { 0 };
+        let trunc_len = lower_limit.max(stack.len().saturating_sub(pop_count));
+        let popped = stack.len() - trunc_len;
+        let popped_any = popped > 0;
+        while stack.len() > trunc_len {
+            let mut old = stack.pop().unwrap();
+            old.on_exit(assets);
+        }
+
+        if news.is_empty() {
+            if popped_any {
+                stack.last_mut().unwrap().on_resume(result, assets);
             }
-            Transition::Pop => {
-                // At 2 or more, we pop down to at least one state
-                // this would be very bad otherwise
-                if stack.len() >= 2 {
-                    stack.pop();
+            return popped;
+        }
+        for (idx, mut new) in news.into_iter().enumerate() {
+            // Whatever's left after popping was already covered by the popped modes
+            if idx > 0 || !popped_any {
+                if let Some(below) = stack.last_mut() {
+                    below.on_pause(assets);
                 }
             }
-            Transition::PopNAndPush(count, mut news) => {
-                let lower_limit = if news.is_empty() { 1 } else { 0 };
-                let trunc_len = lower_limit.max(stack.len() - count);
-                stack.truncate(trunc_len);
+            new.on_enter(assets);
+            stack.push(new);
+        }
+        popped
+    }
+}
 
-                if !news.is_empty() {
-                    stack.append(&mut news);
-                }
+/// A value a popped mode hands back to the mode it reveals.
+///
+/// It can be anything; the receiver checks for the type it expects, like
+/// `result.take::<bool>()` for the answer from a yes/no dialog.
+#[derive(Default)]
+pub struct ModeResult(Option<Box<dyn Any>>);
+
+impl ModeResult {
+    /// No result.
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn new<T: Any>(value: T) -> Self {
+        Self(Some(Box::new(value)))
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Is there a result of this type?
+    pub fn is<T: Any>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Peek at the result, if it's a `T`.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.as_ref().and_then(|value| value.downcast_ref())
+    }
+
+    /// Take the result out, if it's a `T`. Otherwise it's left where it is.
+    pub fn take<T: Any>(&mut self) -> Option<T> {
+        match self.0.take()?.downcast() {
+            Ok(value) => Some(*value),
+            Err(value) => {
+                self.0 = Some(value);
+                None
             }
         }
-        if reveal {
-            stack.last_mut().unwrap().on_resume(assets);
-        }
     }
 }
@@ -0,0 +1,114 @@
+//! Game time, as opposed to wall-clock time.
+
+use crate::controls::secs_to_ticks;
+
+/// The engine's clock.
+///
+/// Everything that animates or times out should go by this instead of
+/// `macroquad::time::get_time`, so pausing, slow motion and replays all work.
+/// Modes see it through `FrameInfo`; to change it, get it with `Resources::clock`.
+///
+/// It advances once per update tick, by `UPDATE_DT` times the timescale.
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    /// How fast game time passes. 1.0 is normal speed, 0.5 is half speed.
+    pub timescale: f32,
+
+    paused: bool,
+    /// How many more ticks to let through while paused
+    steps: u32,
+    /// How many more ticks to freeze for
+    hitstop: u32,
+
+    /// Seconds of game time since the clock started
+    time: f64,
+    /// Seconds of game time the last tick took
+    dt: f32,
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self {
+            timescale: 1.0,
+            paused: false,
+            steps: 0,
+            hitstop: 0,
+            time: 0.0,
+            dt: 0.0,
+        }
+    }
+
+    /// Stop time until `resume` is called.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.steps = 0;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// While paused, let time run for this many more ticks, for stepping through
+    /// frame by frame when debugging.
+    pub fn step(&mut self, ticks: u32) {
+        self.steps += ticks;
+    }
+
+    /// Freeze time for this many seconds, for the little pause that makes a hit feel heavy.
+    ///
+    /// Hit-stop counts down in real time, so it lasts just as long in slow motion.
+    /// If there's already a longer hit-stop going, this does nothing.
+    pub fn hitstop(&mut self, secs: f32) {
+        self.hitstop = self.hitstop.max(secs_to_ticks(secs));
+    }
+
+    /// Is time stopped right now, either by pausing or hit-stop?
+    pub fn frozen(&self) -> bool {
+        self.hitstop > 0 || (self.paused && self.steps == 0)
+    }
+
+    /// Seconds of game time since the clock started.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Seconds of game time the last tick took. Zero if it was frozen.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Advance one update tick that took `real_dt` seconds.
+    /// The engine calls this at the start of every tick.
+    pub fn tick(&mut self, real_dt: f32) {
+        let running = if self.hitstop > 0 {
+            self.hitstop -= 1;
+            false
+        } else if self.paused {
+            if self.steps > 0 {
+                self.steps -= 1;
+                true
+            } else {
+                false
+            }
+        } else {
+            true
+        };
+
+        self.dt = if running {
+            real_dt * self.timescale
+        } else {
+            0.0
+        };
+        self.time += self.dt as f64;
+    }
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
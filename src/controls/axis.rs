@@ -0,0 +1,99 @@
+use enum_map::Enum;
+
+use super::InputCode;
+
+/// The analog controls.
+///
+/// Like `Control`, but with a value from -1.0 to 1.0 instead of on and off.
+#[derive(Debug, Enum, Copy, Clone, PartialEq, Eq)]
+pub enum Axis {
+    MoveX,
+    /// Positive is down, to match the canvas.
+    MoveY,
+}
+
+/// Buttons on a gamepad, by position instead of by label.
+#[derive(Debug, Enum, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum GamepadButton {
+    /// A on Xbox, B on Nintendo, Cross on PlayStation
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog sticks and triggers on a gamepad.
+///
+/// Sticks go from -1.0 to 1.0, with positive Y being down.
+/// Triggers go from 0.0 to 1.0.
+#[derive(Debug, Enum, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Which way a gamepad axis is pushed, for using it as a digital input.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum AxisSign {
+    Negative,
+    Positive,
+}
+
+/// How far a gamepad axis has to be pushed to count as a digital press.
+pub const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Something that moves an `Axis`.
+#[derive(Debug, Copy, Clone)]
+pub enum AxisSource {
+    /// A gamepad axis.
+    /// Values closer to zero than the deadzone are ignored, and the rest are rescaled
+    /// so the output still goes smoothly from 0.0 to 1.0.
+    Gamepad {
+        axis: GamepadAxis,
+        deadzone: f32,
+        invert: bool,
+    },
+    /// Two digital inputs pulling in opposite directions, like A and D.
+    Digital {
+        negative: InputCode,
+        positive: InputCode,
+    },
+}
+
+impl AxisSource {
+    pub fn gamepad(axis: GamepadAxis, deadzone: f32) -> Self {
+        AxisSource::Gamepad {
+            axis,
+            deadzone,
+            invert: false,
+        }
+    }
+
+    pub fn digital(negative: InputCode, positive: InputCode) -> Self {
+        AxisSource::Digital { negative, positive }
+    }
+}
+
+/// Ignore values with a magnitude under the deadzone and rescale the rest to fill the range.
+pub fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= deadzone || deadzone >= 1.0 {
+        0.0
+    } else {
+        value.signum() * ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
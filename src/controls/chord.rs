@@ -0,0 +1,81 @@
+use macroquad::{miniquad::KeyMods, prelude::KeyCode};
+
+use super::InputCode;
+
+/// Which modifier keys are held down.
+#[derive(Debug, Default, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows/Command/Super key
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        logo: false,
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+    pub const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..Modifiers::NONE
+    };
+    pub const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+
+    /// Update these modifiers from a key going up or down.
+    /// Does nothing if the key isn't a modifier key.
+    pub(super) fn update_from_key(&mut self, keycode: KeyCode, down: bool) {
+        let flag = match keycode {
+            KeyCode::LeftShift | KeyCode::RightShift => &mut self.shift,
+            KeyCode::LeftControl | KeyCode::RightControl => &mut self.ctrl,
+            KeyCode::LeftAlt | KeyCode::RightAlt => &mut self.alt,
+            KeyCode::LeftSuper | KeyCode::RightSuper => &mut self.logo,
+            _ => return,
+        };
+        *flag = down;
+    }
+}
+
+impl From<KeyMods> for Modifiers {
+    fn from(mods: KeyMods) -> Self {
+        Self {
+            shift: mods.shift,
+            ctrl: mods.ctrl,
+            alt: mods.alt,
+            logo: mods.logo,
+        }
+    }
+}
+
+/// An input, plus the modifiers that must be held for it to count.
+///
+/// Bind `Chord::new(InputCode::Key(KeyCode::Z), Modifiers::CTRL)` for Ctrl+Z,
+/// or `Chord::new(InputCode::Mouse(MouseButton::Left), Modifiers::SHIFT)` for Shift+Click.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct Chord {
+    pub code: InputCode,
+    pub mods: Modifiers,
+}
+
+impl Chord {
+    pub fn new(code: InputCode, mods: Modifiers) -> Self {
+        Self { code, mods }
+    }
+}
+
+impl From<InputCode> for Chord {
+    /// A chord with no modifiers.
+    fn from(code: InputCode) -> Self {
+        Self::new(code, Modifiers::NONE)
+    }
+}
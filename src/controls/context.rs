@@ -0,0 +1,68 @@
+use enum_map::Enum;
+
+use std::collections::HashMap;
+
+use super::{state::ControlStates, Chord, Control, InputCode, Modifiers};
+
+/// Named sets of bindings.
+///
+/// Each `Gamemode` says which one it reads its controls through, so the same key
+/// can do different things in a menu and in gameplay without any `if`s.
+#[derive(Debug, Enum, Copy, Clone, PartialEq, Eq, Default)]
+pub enum InputContext {
+    #[default]
+    Gameplay,
+    Menu,
+}
+
+/// The bindings for one context, and what state they've put the controls in.
+///
+/// Every context sees every input, so switching contexts never leaves a control stuck down.
+#[derive(Clone)]
+pub(super) struct ActionMap {
+    /// Maps chords to the controls they activate
+    pub bindings: HashMap<Chord, Control>,
+    /// Which control each input currently held down is activating,
+    /// so it lets go of the right one even if the modifiers changed.
+    active_inputs: HashMap<InputCode, Control>,
+    pub states: ControlStates,
+}
+
+impl ActionMap {
+    pub fn new(bindings: HashMap<Chord, Control>) -> Self {
+        Self {
+            bindings,
+            active_inputs: HashMap::new(),
+            states: ControlStates::new(),
+        }
+    }
+
+    /// Call this when an input goes down.
+    ///
+    /// A chord with the exact modifiers held wins; otherwise the input's plain binding
+    /// is used, so Shift+Enter still works as Enter unless it's bound to something else.
+    pub fn input_down(&mut self, code: InputCode, modifiers: Modifiers) {
+        let control = self
+            .bindings
+            .get(&Chord::new(code, modifiers))
+            .or_else(|| self.bindings.get(&Chord::from(code)))
+            .copied();
+        if let Some(control) = control {
+            self.active_inputs.insert(code, control);
+            self.states.down(control);
+        }
+    }
+
+    /// Call this when an input goes up.
+    pub fn input_up(&mut self, code: InputCode) {
+        if let Some(control) = self.active_inputs.remove(&code) {
+            self.states.up(control);
+        }
+    }
+
+    /// Let go of everything.
+    pub fn clear(&mut self) {
+        self.active_inputs.clear();
+        self.states.clear();
+    }
+}
@@ -0,0 +1,732 @@
+mod axis;
+pub use axis::{
+    apply_deadzone, Axis, AxisSign, AxisSource, GamepadAxis, GamepadButton, AXIS_PRESS_THRESHOLD,
+};
+mod chord;
+pub use chord::{Chord, Modifiers};
+mod context;
+pub use context::InputContext;
+mod state;
+pub use state::{secs_to_ticks, RepeatConfig};
+mod touch;
+pub use touch::{DPadDirection, TouchPoint, VirtualButton, VirtualControls, VirtualDPad};
+
+use cogs_gamedev::controls::InputHandler;
+use enum_map::{enum_map, Enum, EnumMap};
+use macroquad::{
+    input::simulate_mouse_with_touch,
+    miniquad::{self, Context, KeyMods, TouchPhase},
+    prelude::{
+        utils::{register_input_subscriber, repeat_all_miniquad_input},
+        vec2, KeyCode, MouseButton, Vec2,
+    },
+};
+
+use crate::{utils::draw::window_to_pixel, UPDATE_DT};
+
+use context::ActionMap;
+use state::ControlStates;
+
+use std::collections::{HashMap, HashSet};
+
+/// The controls
+#[derive(Debug, Enum, Copy, Clone)]
+pub enum Control {
+    Click,
+    Back,
+    Undo,
+    Redo,
+    Restart,
+}
+
+/// Combo keycode, mouse button, and gamepad code
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum InputCode {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// The first finger touching the screen (that isn't on a virtual control)
+    Touch,
+    Gamepad(GamepadButton),
+    /// A gamepad axis pushed past `AXIS_PRESS_THRESHOLD` in one direction.
+    GamepadAxis(GamepadAxis, AxisSign),
+}
+
+/// A single edit to a piece of text, as typed by the player.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextInput {
+    /// A printable character was typed.
+    Char(char),
+    /// Delete the character before the cursor.
+    Backspace,
+    /// Delete the character after the cursor.
+    Delete,
+    /// Move the cursor one character left.
+    Left,
+    /// Move the cursor one character right.
+    Right,
+    /// Move the cursor to the start of the text.
+    Home,
+    /// Move the cursor to the end of the text.
+    End,
+}
+
+impl TextInput {
+    /// Get the edit a key stands for, if it's an editing key.
+    fn from_keycode(keycode: KeyCode) -> Option<Self> {
+        Some(match keycode {
+            KeyCode::Backspace => TextInput::Backspace,
+            KeyCode::Delete => TextInput::Delete,
+            KeyCode::Left => TextInput::Left,
+            KeyCode::Right => TextInput::Right,
+            KeyCode::Home => TextInput::Home,
+            KeyCode::End => TextInput::End,
+            _ => return None,
+        })
+    }
+}
+
+/// Event handler to hook into miniquad and get inputs
+#[derive(Clone)]
+pub struct InputSubscriber {
+    subscriber_id: usize,
+
+    /// The bindings and control states for every context
+    action_maps: EnumMap<InputContext, ActionMap>,
+    /// The context controls are read through right now
+    context: InputContext,
+    /// If this is set, the axes, wheel and typing look idle.
+    /// Used for modes underneath a mode that eats the input.
+    masked: bool,
+    /// Controls that look like they aren't being pressed, for the same reason
+    masked_controls: EnumMap<Control, bool>,
+    /// Every input held down right now, bound or not
+    held_inputs: HashSet<InputCode>,
+    /// Modifier keys held right now
+    modifiers: Modifiers,
+
+    /// How `repeating` repeats
+    repeat_config: RepeatConfig,
+    /// How many ticks apart two presses can be to count as a double tap
+    double_tap_window: u32,
+
+    /// Mouse position in canvas pixels as of this frame
+    mouse_pos: Vec2,
+    /// Has any event said where the mouse is yet?
+    mouse_known: bool,
+    /// How far the mouse moved, in canvas pixels, between last frame and this one
+    mouse_delta: Vec2,
+    /// How far the wheel scrolled this frame
+    mouse_wheel: Vec2,
+    /// Wheel scrolling accumulated from events, before `update` snapshots it
+    wheel_accumulator: Vec2,
+
+    /// Text typed this frame, in order
+    text_input: Vec<TextInput>,
+    /// Text typed since the last `update`
+    text_accumulator: Vec<TextInput>,
+
+    /// Fingers on the screen this tick, in the order they went down
+    touches: Vec<TouchPoint>,
+    /// The finger acting as `InputCode::Touch` and moving the mouse
+    primary_touch: Option<u64>,
+    virtual_controls: VirtualControls,
+
+    /// What moves each axis
+    axis_bindings: EnumMap<Axis, Vec<AxisSource>>,
+    /// The value of each axis as of this tick
+    axis_values: EnumMap<Axis, f32>,
+    /// Raw values of the gamepad axes, as fed in by `inject_gamepad_axis`
+    gamepad_axes: EnumMap<GamepadAxis, f32>,
+}
+
+impl InputSubscriber {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        // the science kid
+        let sid = register_input_subscriber();
+        // We turn touches into clicks ourselves, thank you
+        simulate_mouse_with_touch(false);
+
+        InputSubscriber {
+            subscriber_id: sid,
+
+            action_maps: enum_map! {
+                context => ActionMap::new(Self::default_controls(context)),
+            },
+            context: InputContext::default(),
+            masked: false,
+            masked_controls: EnumMap::default(),
+            held_inputs: HashSet::new(),
+            modifiers: Modifiers::NONE,
+
+            repeat_config: RepeatConfig::default(),
+            double_tap_window: secs_to_ticks(0.25),
+
+            mouse_pos: Vec2::ZERO,
+            mouse_known: false,
+            mouse_delta: Vec2::ZERO,
+            mouse_wheel: Vec2::ZERO,
+            wheel_accumulator: Vec2::ZERO,
+
+            text_input: Vec::new(),
+            text_accumulator: Vec::new(),
+
+            touches: Vec::new(),
+            primary_touch: None,
+            virtual_controls: Self::default_virtual_controls(),
+
+            axis_bindings: Self::default_axes(),
+            axis_values: EnumMap::default(),
+            gamepad_axes: EnumMap::default(),
+        }
+    }
+
+    pub fn default_controls(context: InputContext) -> HashMap<Chord, Control> {
+        let mut controls = HashMap::new();
+
+        // Put your controls here
+        // Use `Chord::new` to bind something that needs modifiers, like Ctrl+Z.
+        controls.insert(InputCode::Mouse(MouseButton::Left).into(), Control::Click);
+        controls.insert(InputCode::Key(KeyCode::Enter).into(), Control::Click);
+        controls.insert(InputCode::Touch.into(), Control::Click);
+        controls.insert(
+            InputCode::Gamepad(GamepadButton::South).into(),
+            Control::Click,
+        );
+
+        // The same input can mean different things in different contexts
+        match context {
+            InputContext::Gameplay => {
+                controls.insert(InputCode::Key(KeyCode::Z).into(), Control::Undo);
+                controls.insert(InputCode::Key(KeyCode::Y).into(), Control::Redo);
+                // Ctrl+Z and Ctrl+Y fall back to the plain keys, but Ctrl+Shift+Z redoes
+                controls.insert(
+                    Chord::new(
+                        InputCode::Key(KeyCode::Z),
+                        Modifiers {
+                            shift: true,
+                            ctrl: true,
+                            ..Modifiers::NONE
+                        },
+                    ),
+                    Control::Redo,
+                );
+                controls.insert(InputCode::Key(KeyCode::R).into(), Control::Restart);
+                controls.insert(
+                    InputCode::Gamepad(GamepadButton::West).into(),
+                    Control::Undo,
+                );
+                controls.insert(
+                    InputCode::Gamepad(GamepadButton::North).into(),
+                    Control::Redo,
+                );
+                controls.insert(
+                    InputCode::Gamepad(GamepadButton::Select).into(),
+                    Control::Restart,
+                );
+            }
+            InputContext::Menu => {
+                controls.insert(InputCode::Key(KeyCode::Escape).into(), Control::Back);
+                controls.insert(
+                    InputCode::Gamepad(GamepadButton::East).into(),
+                    Control::Back,
+                );
+            }
+        }
+
+        controls
+    }
+
+    pub fn default_axes() -> EnumMap<Axis, Vec<AxisSource>> {
+        let mut axes = EnumMap::default();
+
+        // Put your axes here
+        axes[Axis::MoveX] = vec![
+            AxisSource::digital(InputCode::Key(KeyCode::A), InputCode::Key(KeyCode::D)),
+            AxisSource::digital(
+                InputCode::Key(KeyCode::Left),
+                InputCode::Key(KeyCode::Right),
+            ),
+            AxisSource::gamepad(GamepadAxis::LeftX, 0.2),
+        ];
+        axes[Axis::MoveY] = vec![
+            AxisSource::digital(InputCode::Key(KeyCode::W), InputCode::Key(KeyCode::S)),
+            AxisSource::digital(InputCode::Key(KeyCode::Up), InputCode::Key(KeyCode::Down)),
+            AxisSource::gamepad(GamepadAxis::LeftY, 0.2),
+        ];
+
+        axes
+    }
+
+    pub fn default_virtual_controls() -> VirtualControls {
+        // Put your on-screen buttons here, with `VirtualButton::new` and `VirtualDPad::new`.
+        VirtualControls::default()
+    }
+
+    pub fn virtual_controls(&self) -> &VirtualControls {
+        &self.virtual_controls
+    }
+
+    pub fn virtual_controls_mut(&mut self) -> &mut VirtualControls {
+        &mut self.virtual_controls
+    }
+
+    /// Replace everything that moves this axis.
+    pub fn bind_axis(&mut self, axis: Axis, sources: Vec<AxisSource>) {
+        self.axis_bindings[axis] = sources;
+    }
+
+    /// Bind a chord to a control in the given context, replacing whatever it was bound to.
+    pub fn bind(&mut self, context: InputContext, chord: Chord, control: Control) {
+        self.action_maps[context].bindings.insert(chord, control);
+    }
+
+    /// Remove a chord's binding in the given context.
+    pub fn unbind(&mut self, context: InputContext, chord: Chord) {
+        self.action_maps[context].bindings.remove(&chord);
+    }
+
+    /// Let go of every input, like when the window loses focus.
+    pub fn clear_inputs(&mut self) {
+        self.held_inputs.clear();
+        self.modifiers = Modifiers::NONE;
+        for map in self.action_maps.values_mut() {
+            map.clear();
+        }
+    }
+
+    /// The context controls are being read through.
+    pub fn context(&self) -> InputContext {
+        self.context
+    }
+
+    /// Read controls through this context from now on.
+    ///
+    /// The engine calls this with each mode's `Gamemode::input_context` before updating it.
+    pub fn set_context(&mut self, context: InputContext) {
+        self.context = context;
+    }
+
+    /// Is input hidden from whoever's reading it right now?
+    pub fn masked(&self) -> bool {
+        self.masked
+    }
+
+    /// Hide (or stop hiding) all the controls, axes, and typing.
+    ///
+    /// The engine sets this when updating modes under a mode that doesn't pass input through.
+    pub fn set_masked(&mut self, masked: bool) {
+        self.masked = masked;
+        self.masked_controls = enum_map! { _ => masked };
+    }
+
+    /// Is this control hidden from whoever's reading it right now?
+    pub fn control_masked(&self, control: Control) -> bool {
+        self.masked_controls[control]
+    }
+
+    /// Hide (or stop hiding) just these controls, leaving the axes and typing alone.
+    ///
+    /// The engine sets this after `set_masked` for modes under one that passes some controls
+    /// through but not others.
+    pub fn set_masked_controls(&mut self, masked: EnumMap<Control, bool>) {
+        self.masked_controls = masked;
+    }
+
+    pub fn update(&mut self) {
+        let prev_mouse_pos = self.mouse_pos;
+        let mouse_was_known = self.mouse_known;
+
+        self.touches.retain(|touch| !touch.just_ended);
+        for touch in self.touches.iter_mut() {
+            touch.just_started = false;
+        }
+
+        repeat_all_miniquad_input(self, self.subscriber_id);
+        for map in self.action_maps.values_mut() {
+            map.states.update(self.double_tap_window);
+        }
+        self.update_axes();
+
+        // Until the first position comes in the mouse is "at" the origin,
+        // so jumping from there isn't really movement
+        self.mouse_delta = if mouse_was_known {
+            self.mouse_pos - prev_mouse_pos
+        } else {
+            Vec2::ZERO
+        };
+        self.mouse_wheel = self.wheel_accumulator;
+        self.wheel_accumulator = Vec2::ZERO;
+
+        self.text_input.clear();
+        std::mem::swap(&mut self.text_input, &mut self.text_accumulator);
+    }
+
+    /// The control states for the current context.
+    fn states(&self) -> &ControlStates {
+        &self.action_maps[self.context].states
+    }
+
+    /// Did the player let go of this control this tick?
+    pub fn clicked_up(&self, control: Control) -> bool {
+        !self.masked_controls[control] && self.states().released(control)
+    }
+
+    /// How many update ticks this control has been held for, or 0 if it's not held.
+    pub fn held_ticks(&self, control: Control) -> u32 {
+        if self.masked_controls[control] {
+            0
+        } else {
+            self.states().held_ticks(control)
+        }
+    }
+
+    /// How many seconds of game time this control has been held for.
+    pub fn held_secs(&self, control: Control) -> f32 {
+        self.held_ticks(control) as f32 * UPDATE_DT
+    }
+
+    /// Was this control pressed down this tick, shortly after a previous press?
+    pub fn double_tapped(&self, control: Control) -> bool {
+        !self.masked_controls[control] && self.states().double_tapped(control)
+    }
+
+    /// Is this control pressed this tick, or auto-repeating from being held?
+    ///
+    /// Great for menu navigation.
+    pub fn repeating(&self, control: Control) -> bool {
+        self.repeating_with(control, self.repeat_config)
+    }
+
+    /// Like `repeating`, but with a custom repeat config.
+    pub fn repeating_with(&self, control: Control, config: RepeatConfig) -> bool {
+        config.fires(self.held_ticks(control))
+    }
+
+    pub fn set_repeat_config(&mut self, config: RepeatConfig) {
+        self.repeat_config = config;
+    }
+
+    /// Set how many ticks apart two presses can be to count as a double tap.
+    pub fn set_double_tap_window(&mut self, ticks: u32) {
+        self.double_tap_window = ticks;
+    }
+
+    /// The modifier keys held right now.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// All the fingers on the screen this tick, in canvas pixels.
+    ///
+    /// Fingers on virtual controls are included.
+    pub fn touches(&self) -> &[TouchPoint] {
+        &self.touches
+    }
+
+    /// The value of this axis this tick, from -1.0 to 1.0.
+    ///
+    /// If several sources move the axis at once, they're added together.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        if self.masked {
+            0.0
+        } else {
+            self.axis_values[axis]
+        }
+    }
+
+    /// Two axes as a vector, clamped to length 1.
+    ///
+    /// This way holding two keys at once doesn't move diagonally faster.
+    pub fn axis_vec(&self, x: Axis, y: Axis) -> Vec2 {
+        vec2(self.axis(x), self.axis(y)).clamp_length_max(1.0)
+    }
+
+    /// The raw value of a gamepad axis, without any deadzone.
+    pub fn gamepad_axis_raw(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes[axis]
+    }
+
+    /// Feed in a gamepad button going up or down.
+    ///
+    /// Macroquad doesn't read gamepads, so whatever backend does (or a test)
+    /// calls this. It takes effect on the next `update`, same as any other event.
+    pub fn inject_gamepad_button(&mut self, button: GamepadButton, down: bool) {
+        if down {
+            self.input_down(InputCode::Gamepad(button));
+        } else {
+            self.input_up(InputCode::Gamepad(button));
+        }
+    }
+
+    /// Feed in the position of a gamepad axis.
+    ///
+    /// Pushing it past `AXIS_PRESS_THRESHOLD` also presses the matching `InputCode::GamepadAxis`.
+    pub fn inject_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes[axis] = value;
+
+        for (sign, pushed) in [
+            (AxisSign::Negative, value <= -AXIS_PRESS_THRESHOLD),
+            (AxisSign::Positive, value >= AXIS_PRESS_THRESHOLD),
+        ] {
+            let code = InputCode::GamepadAxis(axis, sign);
+            if pushed {
+                self.input_down(code);
+            } else {
+                self.input_up(code);
+            }
+        }
+    }
+
+    /// Recalculate all the axis values from their sources.
+    fn update_axes(&mut self) {
+        for (axis, sources) in self.axis_bindings.iter() {
+            let total: f32 = sources
+                .iter()
+                .map(|source| match *source {
+                    AxisSource::Gamepad {
+                        axis,
+                        deadzone,
+                        invert,
+                    } => {
+                        let value = apply_deadzone(self.gamepad_axes[axis], deadzone);
+                        if invert {
+                            -value
+                        } else {
+                            value
+                        }
+                    }
+                    AxisSource::Digital { negative, positive } => {
+                        let held = |code| {
+                            if self.held_inputs.contains(&code) {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        };
+                        held(positive) - held(negative)
+                    }
+                })
+                .sum();
+            self.axis_values[axis] = total.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Where the mouse is, in canvas pixels.
+    ///
+    /// This can be outside the canvas if the mouse is over the black bars.
+    pub fn mouse_pos(&self) -> Vec2 {
+        self.mouse_pos
+    }
+
+    /// How far the mouse moved in canvas pixels since last frame.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// How far the mouse wheel scrolled this frame.
+    ///
+    /// Positive Y is scrolling up.
+    pub fn mouse_wheel(&self) -> Vec2 {
+        if self.masked {
+            Vec2::ZERO
+        } else {
+            self.mouse_wheel
+        }
+    }
+
+    /// All the text typed this frame, in the order it was typed.
+    ///
+    /// Key repeats are included, so holding backspace deletes lots of things.
+    pub fn text_input(&self) -> &[TextInput] {
+        if self.masked {
+            &[]
+        } else {
+            &self.text_input
+        }
+    }
+
+    /// Just the characters typed this frame, ignoring any editing keys.
+    pub fn typed_chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.text_input().iter().filter_map(|input| match input {
+            TextInput::Char(c) => Some(*c),
+            _ => None,
+        })
+    }
+
+    /// Call this when an input goes down. Every context gets to see it.
+    fn input_down(&mut self, code: InputCode) {
+        if !self.held_inputs.insert(code) {
+            return;
+        }
+        for map in self.action_maps.values_mut() {
+            map.input_down(code, self.modifiers);
+        }
+    }
+
+    /// Call this when an input goes up.
+    fn input_up(&mut self, code: InputCode) {
+        self.held_inputs.remove(&code);
+        for map in self.action_maps.values_mut() {
+            map.input_up(code);
+        }
+    }
+
+    /// Convert a position in the window to canvas pixels and store it.
+    fn set_mouse_pos(&mut self, ctx: &Context, x: f32, y: f32) {
+        self.move_mouse(Self::canvas_pos(ctx, x, y));
+    }
+
+    /// Store a new mouse position, already in canvas pixels.
+    fn move_mouse(&mut self, pos: Vec2) {
+        self.mouse_pos = pos;
+        self.mouse_known = true;
+    }
+
+    /// Convert a position in the window to canvas pixels.
+    fn canvas_pos(ctx: &Context, x: f32, y: f32) -> Vec2 {
+        let (width, height) = ctx.screen_size();
+        let (px, py) = window_to_pixel(x, y, width, height);
+        vec2(px, py)
+    }
+
+    /// Apply presses and releases from the virtual controls.
+    fn apply_virtual_changes(&mut self, changes: Vec<(Control, bool)>) {
+        for (control, down) in changes {
+            for map in self.action_maps.values_mut() {
+                if down {
+                    map.states.down(control);
+                } else {
+                    map.states.up(control);
+                }
+            }
+        }
+    }
+}
+
+impl InputHandler<InputCode, Control> for InputSubscriber {
+    /// Is this input pressed down?
+    /// i.e. is the player pressing the button?
+    fn pressed(&self, control: Control) -> bool {
+        self.held_ticks(control) >= 1
+    }
+
+    /// Is this input released?
+    /// i.e. is the player *not* pressing the button?
+    fn released(&self, control: Control) -> bool {
+        self.held_ticks(control) == 0
+    }
+
+    /// Is this input being clicked down?
+    /// i.e. was it up last frame, but down this frame?
+    fn clicked_down(&self, control: Control) -> bool {
+        self.held_ticks(control) == 1
+    }
+}
+
+impl miniquad::EventHandler for InputSubscriber {
+    fn update(&mut self, _ctx: &mut Context) {}
+
+    fn draw(&mut self, _ctx: &mut Context) {}
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        self.set_mouse_pos(ctx, x, y);
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.wheel_accumulator += vec2(x, y);
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if let Some(edit) = TextInput::from_keycode(keycode) {
+            self.text_accumulator.push(edit);
+        }
+        // The OS says which modifiers are held on every key event, so they can't get stuck
+        // if one was let go while the window didn't have focus.
+        // Look up the binding without this key counting as a modifier,
+        // so binding a lone Shift works.
+        self.modifiers = keymods.into();
+        self.modifiers.update_from_key(keycode, false);
+        // Repeats are done per-tick in `repeating` instead of trusting the OS
+        if !repeat {
+            self.input_down(InputCode::Key(keycode));
+        }
+        self.modifiers.update_from_key(keycode, true);
+    }
+
+    fn char_event(&mut self, _ctx: &mut Context, character: char, keymods: KeyMods, _repeat: bool) {
+        // Ctrl+C and friends are shortcuts, not text
+        if !character.is_control() && !keymods.ctrl && !keymods.logo {
+            self.text_accumulator.push(TextInput::Char(character));
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymods: KeyMods) {
+        self.modifiers = keymods.into();
+        self.modifiers.update_from_key(keycode, false);
+        self.input_up(InputCode::Key(keycode));
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.set_mouse_pos(ctx, x, y);
+        self.input_down(InputCode::Mouse(button));
+    }
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.set_mouse_pos(ctx, x, y);
+        self.input_up(InputCode::Mouse(button));
+    }
+
+    fn touch_event(&mut self, ctx: &mut Context, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        let pos = Self::canvas_pos(ctx, x, y);
+        let mut changes = Vec::new();
+
+        match phase {
+            TouchPhase::Started => {
+                // Must be on a phone; show the on-screen controls
+                self.virtual_controls.visible = true;
+                self.touches.push(TouchPoint {
+                    id,
+                    pos,
+                    just_started: true,
+                    just_ended: false,
+                });
+
+                let captured = self.virtual_controls.touch_started(id, pos, &mut changes);
+                if !captured && self.primary_touch.is_none() {
+                    self.primary_touch = Some(id);
+                    self.move_mouse(pos);
+                    self.input_down(InputCode::Touch);
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(touch) = self.touches.iter_mut().find(|t| t.id == id) {
+                    touch.pos = pos;
+                }
+                self.virtual_controls.touch_moved(id, pos, &mut changes);
+                if self.primary_touch == Some(id) {
+                    self.move_mouse(pos);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(touch) = self.touches.iter_mut().find(|t| t.id == id) {
+                    touch.pos = pos;
+                    touch.just_ended = true;
+                }
+                self.virtual_controls.touch_ended(id, &mut changes);
+                if self.primary_touch == Some(id) {
+                    self.primary_touch = None;
+                    self.move_mouse(pos);
+                    self.input_up(InputCode::Touch);
+                }
+            }
+        }
+
+        self.apply_virtual_changes(changes);
+    }
+}
@@ -0,0 +1,146 @@
+use enum_map::EnumMap;
+
+use crate::UPDATE_DT;
+
+use super::Control;
+
+/// How a held control auto-repeats, like a key held down in a text editor.
+///
+/// All the timings are in update ticks so repeats happen on the same ticks every time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RepeatConfig {
+    /// How many ticks to wait after the first press before repeating
+    pub delay: u32,
+    /// How many ticks between each repeat after that
+    pub interval: u32,
+}
+
+impl RepeatConfig {
+    /// Make a config from times in seconds, rounded to the nearest tick.
+    pub fn from_secs(delay: f32, interval: f32) -> Self {
+        Self {
+            delay: secs_to_ticks(delay),
+            interval: secs_to_ticks(interval).max(1),
+        }
+    }
+
+    /// Does a control that's been held this many ticks fire this tick?
+    pub fn fires(&self, held_ticks: u32) -> bool {
+        match held_ticks {
+            0 => false,
+            1 => true,
+            _ => {
+                let since_press = held_ticks - 1;
+                since_press >= self.delay && (since_press - self.delay) % self.interval.max(1) == 0
+            }
+        }
+    }
+}
+
+impl Default for RepeatConfig {
+    /// 300ms before repeating, then every 80ms.
+    fn default() -> Self {
+        Self::from_secs(0.3, 0.08)
+    }
+}
+
+/// Convert a time in seconds to the nearest number of update ticks.
+pub fn secs_to_ticks(secs: f32) -> u32 {
+    (secs / UPDATE_DT).round().max(0.0) as u32
+}
+
+/// Per-tick bookkeeping of how each control is held.
+///
+/// Events go in with `down` and `up` whenever they arrive; `update` turns them into
+/// the state for the tick.
+#[derive(Clone)]
+pub(super) struct ControlStates {
+    /// How many inputs are holding each control down right now
+    holders: EnumMap<Control, u32>,
+    /// Set when a control goes down, so taps shorter than a tick still count as a press
+    latched: EnumMap<Control, bool>,
+
+    /// How many ticks each control has been held for
+    held_ticks: EnumMap<Control, u32>,
+    /// Was each control released this tick?
+    released: EnumMap<Control, bool>,
+    /// The tick each control was last pressed on
+    last_press: EnumMap<Control, Option<u64>>,
+    /// Was each control pressed twice quickly, ending this tick?
+    double_tapped: EnumMap<Control, bool>,
+
+    /// Number of times `update` has been called
+    tick: u64,
+}
+
+impl ControlStates {
+    pub fn new() -> Self {
+        Self {
+            holders: EnumMap::default(),
+            latched: EnumMap::default(),
+            held_ticks: EnumMap::default(),
+            released: EnumMap::default(),
+            last_press: EnumMap::default(),
+            double_tapped: EnumMap::default(),
+            tick: 0,
+        }
+    }
+
+    pub fn down(&mut self, control: Control) {
+        self.holders[control] += 1;
+        self.latched[control] = true;
+    }
+
+    pub fn up(&mut self, control: Control) {
+        self.holders[control] = self.holders[control].saturating_sub(1);
+    }
+
+    /// Let go of everything, like when the window loses focus.
+    pub fn clear(&mut self) {
+        self.holders = EnumMap::default();
+        self.latched = EnumMap::default();
+    }
+
+    /// Advance one tick. Presses within `double_tap_window` ticks of each other
+    /// count as a double tap.
+    pub fn update(&mut self, double_tap_window: u32) {
+        self.tick += 1;
+        for (control, held) in self.held_ticks.iter_mut() {
+            let active = self.holders[control] > 0 || self.latched[control];
+            self.latched[control] = false;
+
+            self.released[control] = *held > 0 && !active;
+            self.double_tapped[control] = false;
+            if active {
+                *held += 1;
+                if *held == 1 {
+                    if let Some(last) = self.last_press[control] {
+                        if self.tick - last <= double_tap_window as u64 {
+                            self.double_tapped[control] = true;
+                        }
+                    }
+                    // Three quick taps are one double tap and then a fresh tap
+                    self.last_press[control] = if self.double_tapped[control] {
+                        None
+                    } else {
+                        Some(self.tick)
+                    };
+                }
+            } else {
+                *held = 0;
+            }
+        }
+    }
+
+    pub fn held_ticks(&self, control: Control) -> u32 {
+        self.held_ticks[control]
+    }
+
+    pub fn released(&self, control: Control) -> bool {
+        self.released[control]
+    }
+
+    pub fn double_tapped(&self, control: Control) -> bool {
+        self.double_tapped[control]
+    }
+}
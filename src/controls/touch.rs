@@ -0,0 +1,267 @@
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Assets,
+    utils::text::{draw_pixel_text, TextAlign},
+};
+
+use super::Control;
+
+/// A finger on the screen, as of this tick.
+#[derive(Debug, Copy, Clone)]
+pub struct TouchPoint {
+    pub id: u64,
+    /// Position in canvas pixels
+    pub pos: Vec2,
+    /// Did the finger go down this tick?
+    pub just_started: bool,
+    /// Did the finger lift (or get cancelled) this tick?
+    /// It's removed from the list next tick.
+    pub just_ended: bool,
+}
+
+/// On-screen buttons for playing with a touchscreen.
+///
+/// Touches that land on one of these press its control instead of clicking.
+/// They're drawn on top of the canvas once the player touches the screen for the first time.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualControls {
+    pub buttons: Vec<VirtualButton>,
+    pub dpads: Vec<VirtualDPad>,
+    /// Should this be drawn?
+    pub visible: bool,
+}
+
+/// A rectangular on-screen button.
+#[derive(Debug, Clone)]
+pub struct VirtualButton {
+    pub bounds: Rect,
+    pub label: String,
+    pub control: Control,
+    /// The touch pressing this
+    touch: Option<u64>,
+}
+
+impl VirtualButton {
+    pub fn new(bounds: Rect, label: String, control: Control) -> Self {
+        Self {
+            bounds,
+            label,
+            control,
+            touch: None,
+        }
+    }
+
+    pub fn held(&self) -> bool {
+        self.touch.is_some()
+    }
+}
+
+/// Directions on a d-pad.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DPadDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// An on-screen d-pad. Dragging a finger around on it changes which direction is held.
+#[derive(Debug, Clone)]
+pub struct VirtualDPad {
+    pub center: Vec2,
+    /// Distance from the center to the end of each arm
+    pub radius: f32,
+    pub up: Option<Control>,
+    pub down: Option<Control>,
+    pub left: Option<Control>,
+    pub right: Option<Control>,
+    /// The touch on this
+    touch: Option<u64>,
+    /// The direction being held, if any
+    held: Option<DPadDirection>,
+}
+
+impl VirtualDPad {
+    pub fn new(
+        center: Vec2,
+        radius: f32,
+        up: Option<Control>,
+        down: Option<Control>,
+        left: Option<Control>,
+        right: Option<Control>,
+    ) -> Self {
+        Self {
+            center,
+            radius,
+            up,
+            down,
+            left,
+            right,
+            touch: None,
+            held: None,
+        }
+    }
+
+    pub fn held(&self) -> Option<DPadDirection> {
+        self.held
+    }
+
+    pub fn control(&self, dir: DPadDirection) -> Option<Control> {
+        match dir {
+            DPadDirection::Up => self.up,
+            DPadDirection::Down => self.down,
+            DPadDirection::Left => self.left,
+            DPadDirection::Right => self.right,
+        }
+    }
+
+    fn contains(&self, pos: Vec2) -> bool {
+        (pos - self.center).length() <= self.radius
+    }
+
+    /// Which direction a finger here is pressing.
+    /// Near the center is no direction at all.
+    fn direction_at(&self, pos: Vec2) -> Option<DPadDirection> {
+        let offset = pos - self.center;
+        if offset.length() < self.radius * 0.25 {
+            None
+        } else if offset.x.abs() > offset.y.abs() {
+            Some(if offset.x < 0.0 {
+                DPadDirection::Left
+            } else {
+                DPadDirection::Right
+            })
+        } else {
+            Some(if offset.y < 0.0 {
+                DPadDirection::Up
+            } else {
+                DPadDirection::Down
+            })
+        }
+    }
+
+    /// Move the held direction, recording the control changes.
+    fn set_held(&mut self, dir: Option<DPadDirection>, changes: &mut Vec<(Control, bool)>) {
+        if dir == self.held {
+            return;
+        }
+        if let Some(control) = self.held.and_then(|old| self.control(old)) {
+            changes.push((control, false));
+        }
+        if let Some(control) = dir.and_then(|new| self.control(new)) {
+            changes.push((control, true));
+        }
+        self.held = dir;
+    }
+}
+
+impl VirtualControls {
+    /// A finger went down. Pushes `(control, is_down)` for every control it changes.
+    ///
+    /// Returns if one of the virtual controls took the touch.
+    pub(super) fn touch_started(
+        &mut self,
+        id: u64,
+        pos: Vec2,
+        changes: &mut Vec<(Control, bool)>,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        if let Some(button) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.touch.is_none() && b.bounds.contains(pos))
+        {
+            button.touch = Some(id);
+            changes.push((button.control, true));
+            return true;
+        }
+        if let Some(dpad) = self
+            .dpads
+            .iter_mut()
+            .find(|d| d.touch.is_none() && d.contains(pos))
+        {
+            dpad.touch = Some(id);
+            let dir = dpad.direction_at(pos);
+            dpad.set_held(dir, changes);
+            return true;
+        }
+        false
+    }
+
+    /// A finger moved. Buttons stay held even if the finger slides off,
+    /// but d-pads follow the finger.
+    pub(super) fn touch_moved(&mut self, id: u64, pos: Vec2, changes: &mut Vec<(Control, bool)>) {
+        if let Some(dpad) = self.dpads.iter_mut().find(|d| d.touch == Some(id)) {
+            let dir = dpad.direction_at(pos);
+            dpad.set_held(dir, changes);
+        }
+    }
+
+    /// A finger lifted.
+    pub(super) fn touch_ended(&mut self, id: u64, changes: &mut Vec<(Control, bool)>) {
+        for button in self.buttons.iter_mut() {
+            if button.touch == Some(id) {
+                button.touch = None;
+                changes.push((button.control, false));
+            }
+        }
+        for dpad in self.dpads.iter_mut() {
+            if dpad.touch == Some(id) {
+                dpad.touch = None;
+                dpad.set_held(None, changes);
+            }
+        }
+    }
+
+    /// Draw the overlay onto the canvas, if it's visible.
+    pub fn draw(&self, assets: &Assets) {
+        if !self.visible {
+            return;
+        }
+
+        let fill = Color::new(1.0, 1.0, 1.0, 0.25);
+        let fill_held = Color::new(1.0, 1.0, 1.0, 0.5);
+        let border = Color::new(0.0, 0.0, 0.0, 0.5);
+
+        for button in self.buttons.iter() {
+            let Rect { x, y, w, h } = button.bounds;
+            let color = if button.held() { fill_held } else { fill };
+            draw_rectangle(x.round(), y.round(), w.round(), h.round(), color);
+            draw_rectangle_lines(x.round(), y.round(), w.round(), h.round(), 1.0, border);
+            draw_pixel_text(
+                &button.label,
+                (x + w / 2.0).round(),
+                (y + h / 2.0 - 2.5).round(),
+                TextAlign::Center,
+                border,
+                assets.textures.fonts.small,
+            );
+        }
+
+        for dpad in self.dpads.iter() {
+            let arm = (dpad.radius * 2.0 / 3.0).round();
+            let half = (arm / 2.0).round();
+            let center = dpad.center.round();
+            let (cx, cy) = (center.x, center.y);
+            for (dir, x, y) in [
+                (DPadDirection::Up, cx - half, cy - half - arm),
+                (DPadDirection::Down, cx - half, cy + half),
+                (DPadDirection::Left, cx - half - arm, cy - half),
+                (DPadDirection::Right, cx + half, cy - half),
+            ] {
+                let color = if dpad.held == Some(dir) {
+                    fill_held
+                } else {
+                    fill
+                };
+                draw_rectangle(x, y, arm, arm, color);
+                draw_rectangle_lines(x, y, arm, arm, 1.0, border);
+            }
+            draw_rectangle(cx - half, cy - half, arm, arm, fill);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Messages between modes and services that don't know about each other.
+
+use std::any::Any;
+
+/// A queue of typed events.
+///
+/// Anything can publish an event, like a `LevelCompleted` or a `PlaySound`.
+/// Events published during one tick can be read by everything during the next tick,
+/// then they're gone. Reading doesn't use an event up, so any number of things can
+/// listen for the same type.
+///
+/// Events come out in exactly the order they went in. Services update in the order
+/// they were added and modes from the top of the stack down, so the same inputs
+/// always produce the same events in the same order, and replays stay in sync.
+///
+/// The engine keeps one in the `Resources`; get it with `Resources::events`.
+#[derive(Default)]
+pub struct EventBus {
+    /// Events published this tick
+    pending: Vec<Box<dyn Any>>,
+    /// Events published last tick, which can be read now
+    readable: Vec<Box<dyn Any>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send an event, to be read next tick.
+    pub fn publish<T: Any>(&mut self, event: T) {
+        self.pending.push(Box::new(event));
+    }
+
+    /// Every event of this type published last tick, in the order they were published.
+    pub fn read<T: Any>(&self) -> impl Iterator<Item = &T> + '_ {
+        self.readable
+            .iter()
+            .filter_map(|event| event.downcast_ref())
+    }
+
+    /// Was an event of this type published last tick?
+    pub fn any<T: Any>(&self) -> bool {
+        self.read::<T>().next().is_some()
+    }
+
+    /// Move on to the next tick, making this tick's events readable.
+    /// The engine calls this at the start of every tick.
+    pub fn advance(&mut self) {
+        self.readable = std::mem::take(&mut self.pending);
+    }
+}
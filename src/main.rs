@@ -1,9 +1,17 @@
 #![feature(try_blocks)]
+#![cfg_attr(test, feature(test))]
 
 pub mod assets;
 pub mod boilerplates;
+pub mod clock;
 pub mod controls;
+pub mod events;
+pub mod mode_stack;
 pub mod modes;
+pub mod resources;
+pub mod rng;
+pub mod services;
+pub mod transition_fx;
 pub mod utils;
 
 // `getrandom` doesn't support WASM so we use quadrand's rng for it.
@@ -12,10 +20,14 @@ mod wasm_random_impl;
 
 use crate::{
     assets::Assets,
-    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer},
+    boilerplates::FrameInfo,
     controls::InputSubscriber,
-    modes::{DispatchMode, ModeLogo},
-    utils::draw::width_height_deficit,
+    mode_stack::ModeStack,
+    modes::ModeLogo,
+    resources::Resources,
+    services::Services,
+    transition_fx::FxTargets,
+    utils::draw::{canvas_camera, width_height_deficit},
 };
 
 use ::rand::Rng;
@@ -94,35 +106,45 @@ async fn gameloop() {
     // Drawing must happen on the main thread (thanks macroquad...)
     // so updating goes over here
     let _update_handle = thread::spawn(move || {
-        let mut mode_stack: Vec<DispatchMode> = vec![ModeLogo::new().into()];
+        let mut mode_stack = ModeStack::new(ModeLogo::new().into(), assets);
+        let mut resources = Resources::new();
+        let mut services = Services::new();
         let mut frame_info = FrameInfo {
             dt: UPDATE_DT,
+            time: 0.0,
             frames_ran: 0,
         };
 
         loop {
             if frame_info.frames_ran < ENTROPY_ACCUMULATE_TIME {
-                let (mx, my) = mouse_position();
+                // Don't poll macroquad from this thread; the subscriber has the mouse already.
+                let mouse = controls.mouse_pos();
                 macroquad::rand::srand(
-                    QuadRand.gen::<u64>() ^ (((mx.to_bits() as u64) << 32) | my.to_bits() as u64),
+                    QuadRand.gen::<u64>()
+                        ^ (((mouse.x.to_bits() as u64) << 32) | mouse.y.to_bits() as u64),
                 );
             }
 
             controls.update();
+            resources.events().advance();
+            let clock = resources.clock();
+            clock.tick(UPDATE_DT);
+            frame_info.dt = clock.dt();
+            frame_info.time = clock.time();
+
+            services.update(&controls, frame_info, assets, &mut resources);
             // Update the current state.
             // To change state, return a non-None transition.
-            let transition = mode_stack
-                .last_mut()
-                .unwrap()
-                .update(&controls, frame_info, assets);
-            transition.apply(&mut mode_stack, assets);
+            mode_stack.update(&mut controls, frame_info, assets, &mut resources);
 
             #[allow(clippy::modulo_one)]
             if frame_info.frames_ran % UPDATES_PER_DRAW == 0 {
-                let drawer = mode_stack.last_mut().unwrap().get_draw_info();
+                let draw_info = mode_stack.draw_info();
+                let service_overlays = services.draw_overlays();
+                let overlay = controls.virtual_controls().clone();
                 // Wait on the draw thread to finish up drawing, then send.
                 // Ignore the error
-                let _ = draw_tx.send(drawer);
+                let _ = draw_tx.send((draw_info, service_overlays, overlay, frame_info.time));
             }
             frame_info.frames_ran += 1;
         }
@@ -130,16 +152,18 @@ async fn gameloop() {
 
     let canvas = render_target(WIDTH as u32, HEIGHT as u32);
     canvas.texture.set_filter(FilterMode::Nearest);
+    let fx_targets = FxTargets::new(canvas);
 
     // Draw loop
     let mut frame_info = FrameInfo {
         dt: 0.0,
+        time: 0.0,
         frames_ran: 0,
     };
     loop {
         frame_info.dt = macroquad::time::get_frame_time();
 
-        let drawer = match draw_rx.try_recv() {
+        let (draw_info, service_overlays, overlay, time) = match draw_rx.try_recv() {
             Ok(it) => it,
             Err(TryRecvError::Empty) => {
                 eprintln!("Waiting on updates!");
@@ -147,18 +171,16 @@ async fn gameloop() {
             }
             Err(TryRecvError::Disconnected) => panic!("The draw channel closed!"),
         };
+        frame_info.time = time;
 
         // Draw the state.
         push_camera_state();
-        set_camera(&Camera2D {
-            render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-            ..Default::default()
-        });
+        set_camera(&canvas_camera(canvas));
 
         clear_background(WHITE);
-        drawer.draw(assets, frame_info);
+        draw_info.draw(&fx_targets, assets, frame_info);
+        service_overlays.draw(assets, frame_info);
+        overlay.draw(assets);
 
         // Done rendering to the canvas; go back to our normal camera
         // to size the canvas
@@ -195,52 +217,51 @@ async fn gameloop() {
     let assets = Box::leak(Box::new(assets)) as &'static Assets;
 
     let mut controls = InputSubscriber::new();
-    let mut mode_stack: Vec<DispatchMode> = vec![ModeLogo::new().into()];
+    let mut mode_stack = ModeStack::new(ModeLogo::new().into(), assets);
+    let mut resources = Resources::new();
+    let mut services = Services::new();
 
     let canvas = render_target(WIDTH as u32, HEIGHT as u32);
     canvas.texture.set_filter(FilterMode::Nearest);
+    let fx_targets = FxTargets::new(canvas);
 
     let mut frame_info = FrameInfo {
         dt: UPDATE_DT,
+        time: 0.0,
         frames_ran: 0,
     };
     loop {
         if frame_info.frames_ran < ENTROPY_ACCUMULATE_TIME {
-            let (mx, my) = mouse_position();
+            let mouse = controls.mouse_pos();
             macroquad::rand::srand(
-                QuadRand.gen::<u64>() ^ (((mx.to_bits() as u64) << 32) | my.to_bits() as u64),
+                QuadRand.gen::<u64>()
+                    ^ (((mouse.x.to_bits() as u64) << 32) | mouse.y.to_bits() as u64),
             );
         }
 
-        frame_info.dt = UPDATE_DT;
-
         // Update the current state.
         // To change state, return a non-None transition.
         for _ in 0..UPDATES_PER_DRAW {
             controls.update();
-
-            let transition = mode_stack
-                .last_mut()
-                .unwrap()
-                .update(&controls, frame_info, assets);
-            transition.apply(&mut mode_stack, assets);
+            resources.events().advance();
+            let clock = resources.clock();
+            clock.tick(UPDATE_DT);
+            frame_info.dt = clock.dt();
+            frame_info.time = clock.time();
+
+            services.update(&controls, frame_info, assets, &mut resources);
+            mode_stack.update(&mut controls, frame_info, assets, &mut resources);
         }
 
         frame_info.dt = macroquad::time::get_frame_time();
 
         push_camera_state();
-        // These divides and multiplies are required to get the camera in the center of the screen
-        // and having it fill everything.
-        set_camera(&Camera2D {
-            render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-            ..Default::default()
-        });
+        set_camera(&canvas_camera(canvas));
         clear_background(WHITE);
         // Draw the state.
-        let drawer = mode_stack.last_mut().unwrap().get_draw_info();
-        drawer.draw(assets, frame_info);
+        mode_stack.draw_info().draw(&fx_targets, assets, frame_info);
+        services.draw_overlays().draw(assets, frame_info);
+        controls.virtual_controls().draw(assets);
 
         // Done rendering to the canvas; go back to our normal camera
         // to size the canvas
@@ -272,8 +293,9 @@ async fn gameloop() {
 mod prelude {
     pub use crate::assets::Assets;
     pub use crate::boilerplates::*;
-    pub use crate::controls::{Control, InputSubscriber};
+    pub use crate::controls::{Control, InputContext, InputSubscriber};
     pub use crate::modes::{DispatchDrawer, DispatchMode};
+    pub use crate::resources::Resources;
     pub use crate::utils;
 
     pub use crate::{ASPECT_RATIO, FRAMERATE, HEIGHT, WIDTH};
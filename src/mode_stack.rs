@@ -0,0 +1,234 @@
+//! The stack of modes the game runs, and the transition effects between them.
+
+use anyhow::bail;
+use enum_map::{enum_map, EnumMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::Assets,
+    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    modes::{DispatchDrawer, DispatchMode},
+    resources::Resources,
+    transition_fx::{EffectSnapshot, FxTargets, RunningEffect},
+    utils::{
+        save,
+        serdeflate::{binzip, unbinzip},
+    },
+};
+
+/// All the modes running, with the top one last.
+pub struct ModeStack {
+    modes: Vec<DispatchMode>,
+    /// Each mode's clock: how many seconds of game time it's been updated for
+    clocks: Vec<f64>,
+    /// The transition effect playing right now, if any
+    effect: Option<RunningEffect>,
+}
+
+impl ModeStack {
+    /// Make a stack with just this mode, calling its `on_enter`.
+    pub fn new(mut first: DispatchMode, assets: &Assets) -> Self {
+        first.on_enter(assets);
+        Self {
+            modes: vec![first],
+            clocks: vec![0.0],
+            effect: None,
+        }
+    }
+
+    pub fn modes(&self) -> &[DispatchMode] {
+        &self.modes
+    }
+
+    /// Is a transition effect playing?
+    pub fn in_effect(&self) -> bool {
+        self.effect.is_some()
+    }
+
+    /// Update the stack for one tick, then apply the transition the top mode returns.
+    ///
+    /// The top mode is always updated. Then, as long as the mode just updated passes any input
+    /// through or doesn't block updates, the next one down is updated too.
+    /// Each mode only sees the controls every mode above it let through.
+    ///
+    /// While an effect is playing the modes still update, but none of them get any input.
+    ///
+    /// `frame_info` has the engine clock's time in it; each mode gets its own clock instead.
+    pub fn update(
+        &mut self,
+        controls: &mut InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+        resources: &mut Resources,
+    ) {
+        let mut transition = Transition::None;
+        let mut masked = self.effect.is_some();
+        let mut masked_controls: EnumMap<Control, bool> = enum_map! { _ => masked };
+        let mut time_paused = false;
+        let modes = self.modes.iter_mut().zip(self.clocks.iter_mut());
+        for (depth, (mode, clock)) in modes.rev().enumerate() {
+            let dt = if time_paused { 0.0 } else { frame_info.dt };
+            *clock += dt as f64;
+            let mode_frame_info = FrameInfo {
+                dt,
+                time: *clock,
+                ..frame_info
+            };
+
+            controls.set_context(mode.input_context());
+            controls.set_masked(masked);
+            controls.set_masked_controls(masked_controls);
+            let mode_transition = mode.update(controls, mode_frame_info, assets, resources);
+            if depth == 0 {
+                transition = mode_transition;
+            }
+
+            time_paused |= mode.pauses_below();
+            let mut passes_any = mode.passes_input();
+            masked |= !passes_any;
+            for (control, control_masked) in masked_controls.iter_mut() {
+                let passes = mode.passes_control(control);
+                passes_any |= passes;
+                *control_masked |= !passes;
+            }
+            if mode.blocks_updates() && !passes_any {
+                break;
+            }
+        }
+        controls.set_masked(false);
+
+        if let Some(effect) = &mut self.effect {
+            if effect.tick() {
+                self.effect = None;
+            }
+        }
+
+        let popped = match transition {
+            Transition::WithEffect(fx, inner) => {
+                // Remember what things looked like before the transition
+                let outgoing = self.gather_drawers();
+                let popped = inner.apply(&mut self.modes, assets);
+                self.effect = Some(RunningEffect::new(fx, outgoing));
+                popped
+            }
+            transition => transition.apply(&mut self.modes, assets),
+        };
+        // New modes' clocks start at zero
+        self.clocks.truncate(self.clocks.len() - popped);
+        self.clocks.resize(self.modes.len(), 0.0);
+        // Make sure the context is right for whatever's on top now
+        controls.set_context(self.modes.last().unwrap().input_context());
+    }
+
+    /// Save the stack to gzipped bincode.
+    ///
+    /// Saving stops at the first mode that opts out of it, so what's saved is the stack as it was
+    /// before that mode was pushed. (Any effect that's playing is left out too.)
+    pub fn save(&self, assets: &Assets) -> anyhow::Result<Vec<u8>> {
+        let count = self
+            .modes
+            .iter()
+            .position(|mode| !mode.saveable())
+            .unwrap_or(self.modes.len());
+        if count == 0 {
+            bail!("The bottom mode `{}` can't be saved", self.modes[0].name());
+        }
+        let saved = SavedStack {
+            modes: &self.modes[..count],
+            clocks: &self.clocks[..count],
+        };
+        save::with_assets(assets, || binzip(&saved))
+    }
+
+    /// Load a stack from what `save` returned.
+    ///
+    /// The lifecycle hooks run as if the modes were pushed one at a time from the bottom up:
+    /// each one is entered, and then paused when the next one goes on top of it.
+    pub fn load(data: &[u8], assets: &Assets) -> anyhow::Result<Self> {
+        let LoadedStack {
+            modes: saved,
+            clocks,
+        } = save::with_assets(assets, || unbinzip(data))?;
+        if saved.is_empty() {
+            bail!("The saved stack has no modes in it");
+        }
+        if saved.len() != clocks.len() {
+            bail!("The saved stack has a different number of modes and clocks");
+        }
+
+        let mut modes: Vec<DispatchMode> = Vec::with_capacity(saved.len());
+        for mut mode in saved {
+            if let Some(below) = modes.last_mut() {
+                below.on_pause(assets);
+            }
+            mode.on_enter(assets);
+            modes.push(mode);
+        }
+
+        Ok(Self {
+            modes,
+            clocks,
+            effect: None,
+        })
+    }
+
+    /// Get everything needed to draw the stack this frame.
+    pub fn draw_info(&mut self) -> StackDrawInfo {
+        StackDrawInfo {
+            drawers: self.gather_drawers(),
+            effect: self.effect.as_ref().map(|effect| effect.snapshot()),
+        }
+    }
+
+    /// Get drawers for every visible mode, from the bottom up.
+    ///
+    /// That's the highest mode that isn't transparent and everything above it.
+    fn gather_drawers(&mut self) -> Vec<DispatchDrawer> {
+        let bottom = self
+            .modes
+            .iter()
+            .rposition(|mode| !mode.is_transparent())
+            .unwrap_or(0);
+        self.modes[bottom..]
+            .iter_mut()
+            .map(|mode| mode.get_draw_info())
+            .collect()
+    }
+}
+
+/// How to draw a `ModeStack` for one frame.
+pub struct StackDrawInfo {
+    drawers: Vec<DispatchDrawer>,
+    effect: Option<EffectSnapshot>,
+}
+
+impl StackDrawInfo {
+    /// Draw onto the canvas. The canvas camera should already be set,
+    /// and is still set afterwards.
+    pub fn draw(&self, targets: &FxTargets, assets: &Assets, frame_info: FrameInfo) {
+        match &self.effect {
+            Some(effect) => effect.draw(&self.drawers, targets, assets, frame_info),
+            None => {
+                for drawer in self.drawers.iter() {
+                    drawer.draw(assets, frame_info);
+                }
+            }
+        }
+    }
+}
+
+/// The part of a stack that gets saved.
+#[derive(Serialize)]
+struct SavedStack<'a> {
+    modes: &'a [DispatchMode],
+    /// Each mode's clock
+    clocks: &'a [f64],
+}
+
+/// A `SavedStack` read back in.
+#[derive(Deserialize)]
+struct LoadedStack {
+    modes: Vec<DispatchMode>,
+    clocks: Vec<f64>,
+}
@@ -0,0 +1,49 @@
+//! How much making a drawer for `ModeExample` costs, before and after its state was `Shared`.
+//!
+//! Run with `cargo bench`.
+//! There's no window, so the textures are all `Texture2D::empty()`; nothing here draws.
+
+extern crate test;
+
+use macroquad::prelude::Texture2D;
+use test::{black_box, Bencher};
+
+use std::mem;
+
+use super::ModeExample;
+use crate::{assets::Fonts, boilerplates::Gamemode, utils::text::Billboard};
+
+fn mode() -> ModeExample {
+    let fonts = Fonts {
+        small: Texture2D::empty(),
+        medium: Texture2D::empty(),
+    };
+    ModeExample::with_textures(&fonts, Texture2D::empty(), 1)
+}
+
+/// What the drawer used to cost, when it was `self.clone()` over a plain `Vec<Billboard>`.
+#[bench]
+fn before_deep_clone(b: &mut Bencher) {
+    let mode = mode();
+    let billboards: Vec<Billboard> = mode.billboards.iter().map(|bb| (**bb).clone()).collect();
+    b.iter(|| black_box((billboards.clone(), mode.time)));
+}
+
+/// Nothing changed since the last frame.
+#[bench]
+fn after_unchanged(b: &mut Bencher) {
+    let mut mode = mode();
+    b.iter(|| black_box(mode.get_draw_info()));
+}
+
+/// One billboard changes every frame while last frame's drawer is still alive,
+/// so only that billboard and the outer `Vec` are copied.
+#[bench]
+fn after_one_changed(b: &mut Bencher) {
+    let mut mode = mode();
+    let mut last_drawer = mode.get_draw_info();
+    b.iter(|| {
+        mode.billboards.make_mut()[3].make_mut().pos.y += 1.0;
+        black_box(mem::replace(&mut last_drawer, mode.get_draw_info()));
+    });
+}
@@ -1,35 +1,54 @@
 use cogs_gamedev::controls::InputHandler;
-use macroquad::prelude::{clear_background, vec2, BLACK, GREEN, WHITE};
+use macroquad::prelude::{clear_background, vec2, Texture2D, BLACK, GREEN, WHITE};
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod bench;
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, Fonts},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
+    modes::DispatchDrawer,
+    resources::Resources,
     utils::{
-        draw::{self, mouse_position_pixel},
+        draw,
         profile::PersistentStorage,
+        snapshot::Shared,
         text::{Billboard, Markup, TextSpan, Wave},
     },
-    modes::DispatchDrawer,
 };
 
 /// Example gamemode that draws a cool billboard demo
-#[derive(Clone)]
+///
+/// The billboards are `Shared` so handing out a drawer every frame doesn't copy all their text,
+/// and each one is `Shared` on its own so changing one doesn't copy the others.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ModeExample {
-    billboards: Vec<Billboard>,
+    billboards: Shared<Vec<Shared<Billboard>>>,
+    /// Game time as of the last update
+    time: f64,
 }
 
 impl ModeExample {
     pub fn new(assets: &Assets) -> Self {
         let mut profile = PersistentStorage::get();
         profile.open_count += 1;
+        Self::with_textures(
+            &assets.textures.fonts,
+            assets.textures.billboard_patch9,
+            profile.open_count,
+        )
+    }
 
+    /// Everything `new` does that doesn't need the profile or the rest of the assets.
+    fn with_textures(fonts: &Fonts, patch9: Texture2D, open_count: u64) -> Self {
         let main_board = Billboard::new(
             vec![
                 TextSpan::new(
                     "Welcome to the ".to_owned(),
                     Markup {
-                        font: assets.textures.fonts.medium,
+                        font: fonts.medium,
                         color: BLACK,
                         kerning: 1.0,
                         vert_space: 1.0,
@@ -39,7 +58,7 @@ impl ModeExample {
                 TextSpan::new(
                     "Omegaquad Demo!\n".to_owned(),
                     Markup {
-                        font: assets.textures.fonts.medium,
+                        font: fonts.medium,
                         color: GREEN,
                         kerning: 1.0,
                         vert_space: 1.0,
@@ -53,7 +72,7 @@ impl ModeExample {
                 TextSpan::new(
                     "\n\nThe quick brown fox jumps over the lazy dog.".to_string(),
                     Markup {
-                        font: assets.textures.fonts.medium,
+                        font: fonts.medium,
                         color: WHITE,
                         kerning: 1.0,
                         vert_space: 1.0,
@@ -63,7 +82,7 @@ impl ModeExample {
                 TextSpan::new(
                     "\nJackdaws love my big sphinx of quartz.\n\n".to_string(),
                     Markup {
-                        font: assets.textures.fonts.small,
+                        font: fonts.small,
                         color: WHITE,
                         kerning: 1.0,
                         vert_space: 1.0,
@@ -73,7 +92,7 @@ impl ModeExample {
             ],
             vec2(16.0, 16.0),
             vec2(6.0, 16.0),
-            assets.textures.billboard_patch9,
+            patch9,
             16.0,
             18,
             4,
@@ -83,52 +102,56 @@ impl ModeExample {
 
         let marked_up = 
                     Billboard::from_markup(String::from(
-                        "[$v4.0$Here is my [$cb00b69$fancy, [$w0.4,1.0,0.1$wavy [$cff000088$markup$w]\nthing$c]. How nice.$c] Cool demo?\n[$k3.0$!@#$%^&*()$k]$v]"), assets.textures.fonts.medium).unwrap();
+                        "[$v4.0$Here is my [$cb00b69$fancy, [$w0.4,1.0,0.1$wavy [$cff000088$markup$w]\nthing$c]. How nice.$c] Cool demo?\n[$k3.0$!@#$%^&*()$k]$v]"), fonts.medium).unwrap();
 
         let counter = Billboard::from_markup(
             format!(
                 "YOU HAVE\nOPENED THIS\nDEMO [$c00ffff${}$c]\nTIME(S) :)",
-                profile.open_count
+                open_count
             ),
-            assets.textures.fonts.small,
+            fonts.small,
         )
         .unwrap();
 
-        let clicker =
-            Billboard::from_markup("Click on me!".to_string(), assets.textures.fonts.medium)
-                .unwrap();
+        let clicker = Billboard::from_markup("Click on me!".to_string(), fonts.medium).unwrap();
 
         Self {
-            billboards: vec![
-                main_board,
-                Billboard::new(
-                    marked_up,
-                    vec2(16.0, 84.0),
-                    vec2(6.0, 16.0),
-                    assets.textures.billboard_patch9,
-                    16.0,
-                    13,
-                    3,
-                ),
-                Billboard::new(
-                    counter,
-                    vec2(16.0 * 14.0 + 8.0, 84.0),
-                    vec2(6.0, 16.0),
-                    assets.textures.billboard_patch9,
-                    16.0,
-                    4,
-                    3,
-                ),
-                Billboard::new(
-                    clicker,
-                    vec2(16.0, 160.0),
-                    vec2(6.0, 16.0),
-                    assets.textures.billboard_patch9,
-                    16.0,
-                    15,
-                    3,
-                ),
-            ],
+            billboards: Shared::new(
+                vec![
+                    main_board,
+                    Billboard::new(
+                        marked_up,
+                        vec2(16.0, 84.0),
+                        vec2(6.0, 16.0),
+                        patch9,
+                        16.0,
+                        13,
+                        3,
+                    ),
+                    Billboard::new(
+                        counter,
+                        vec2(16.0 * 14.0 + 8.0, 84.0),
+                        vec2(6.0, 16.0),
+                        patch9,
+                        16.0,
+                        4,
+                        3,
+                    ),
+                    Billboard::new(
+                        clicker,
+                        vec2(16.0, 160.0),
+                        vec2(6.0, 16.0),
+                        patch9,
+                        16.0,
+                        15,
+                        3,
+                    ),
+                ]
+                .into_iter()
+                .map(Shared::new)
+                .collect(),
+            ),
+            time: 0.0,
         }
     }
 }
@@ -137,13 +160,15 @@ impl Gamemode for ModeExample {
     fn update(
         &mut self,
         controls: &InputSubscriber,
-        _frame_info: FrameInfo,
+        frame_info: FrameInfo,
         assets: &Assets,
+        _resources: &mut Resources,
     ) -> Transition {
+        self.time = frame_info.time;
+
         if controls.clicked_down(Control::Click) {
-            let (mx, my) = mouse_position_pixel();
             let mut msg = if let Some((span, cidx, c)) =
-                self.billboards[3].get_char_at_pixel(vec2(mx, my), 1.0)
+                self.billboards[3].get_char_at_pixel(controls.mouse_pos(), 1.0, self.time)
             {
                 format!("You clicked on [$cff0000$Span #{}$c], [$c00ff00$Char #{}$c],\nwhich was a `[$cffff00${}$c]`.", span, cidx, c as char)
             } else {
@@ -151,7 +176,7 @@ impl Gamemode for ModeExample {
             };
             msg += "[$v6.0$\n$v]Anyways, here's some more [$w1,1,0$filler text$w].";
 
-            self.billboards[3].text =
+            self.billboards.make_mut()[3].make_mut().text =
                 Billboard::from_markup(msg, assets.textures.fonts.medium).unwrap();
         }
 
@@ -168,7 +193,7 @@ impl GamemodeDrawer for ModeExample {
         clear_background(draw::hexcolor(0x110011ff));
 
         for bb in self.billboards.iter() {
-            bb.draw();
+            bb.draw(self.time);
         }
     }
 }
@@ -3,12 +3,17 @@ use crate::{
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
     modes::DispatchDrawer,
-    utils::draw::{self, hexcolor},
+    resources::Resources,
+    transition_fx::TransitionEffect,
+    utils::{
+        draw::{self, hexcolor},
+        tween::{Animation, Ease, Tween},
+    },
     HEIGHT, WIDTH,
 };
 
 use cogs_gamedev::{chance::WeightedPicker, controls::InputHandler};
-use macroquad::prelude::Color;
+use macroquad::prelude::{Color, BLACK};
 use quad_rand::compat::QuadRand;
 use rand::Rng;
 
@@ -17,11 +22,14 @@ use std::f32::consts::TAU;
 use super::ModeExample;
 
 const BANNER_DISPLAY_SIZE: f32 = WIDTH * 0.6;
-const BANNER_START_TIME: f64 = 0.25;
+const BANNER_START_TIME: f32 = 0.25;
+const BANNER_END_TIME: f32 = 0.6;
+const BANNER_FRAMES: usize = 8;
 
 #[derive(Clone)]
 pub struct ModeLogo {
-    start_time: f64,
+    /// Game time this has been running for
+    time: f64,
     first_frame: bool,
 
     blades: usize,
@@ -69,7 +77,7 @@ impl ModeLogo {
         );
 
         Self {
-            start_time: 0.0,
+            time: 0.0,
             first_frame: true,
 
             blades,
@@ -84,22 +92,22 @@ impl Gamemode for ModeLogo {
     fn update(
         &mut self,
         controls: &InputSubscriber,
-        _frame_info: FrameInfo,
+        frame_info: FrameInfo,
         assets: &Assets,
+        _resources: &mut Resources,
     ) -> Transition {
+        self.time = frame_info.time;
         if self.first_frame {
             self.first_frame = false;
-            self.start_time = macroquad::time::get_time();
             macroquad::audio::play_sound_once(assets.sounds.logo_jingle);
         }
 
-        if macroquad::time::get_time() - self.start_time > 5.0
-            || controls.clicked_down(Control::Click)
-        {
+        if self.time > 5.0 || controls.clicked_down(Control::Click) {
             macroquad::audio::stop_sound(assets.sounds.logo_jingle);
 
             // Put your next state here!
             Transition::Swap(ModeExample::new(assets).into())
+                .with_effect(TransitionEffect::FadeToColor(BLACK), 0.5)
         } else {
             Transition::None
         }
@@ -117,7 +125,7 @@ impl GamemodeDrawer for ModeLogo {
 
         let background = draw::hexcolor(0x21181bff);
 
-        let time_ran = macroquad::time::get_time() - self.start_time;
+        let time_ran = self.time;
 
         let bg_color = if time_ran < 0.52 {
             background
@@ -143,11 +151,14 @@ impl GamemodeDrawer for ModeLogo {
             }
         }
 
-        let banner_idx = if time_ran < BANNER_START_TIME {
-            0
-        } else {
-            (((time_ran - BANNER_START_TIME) * 8.0 / (0.6 - BANNER_START_TIME)) as usize).min(7)
-        };
+        let banner_frame = Tween::new(
+            0.0,
+            BANNER_FRAMES as f32,
+            BANNER_END_TIME - BANNER_START_TIME,
+            Ease::Linear,
+        )
+        .delayed(BANNER_START_TIME);
+        let banner_idx = (banner_frame.sample(time_ran as f32) as usize).min(BANNER_FRAMES - 1);
         let sx = banner_idx as f32 * 64.0;
         draw_texture_ex(
             assets.textures.title_banner,
@@ -6,14 +6,186 @@ pub use logo::ModeLogo;
 mod example;
 pub use example::ModeExample;
 
-#[enum_dispatch(Gamemode)]
-pub enum DispatchMode {
-    ModeLogo,
-    ModeExample,
+/// Register every mode in one place.
+///
+/// Each entry gives the mode's type, the name it's looked up by, the type of its drawer,
+/// and how to make it from the assets (a function or closure; its return value gets `into`ed).
+///
+/// Modes are saved with serde, so their types need to implement `Serialize` and `Deserialize`
+/// (see `utils::save` for fields holding assets).
+/// Modes that can't be saved opt out with `save: false`.
+///
+/// This generates the `DispatchMode` and `DispatchDrawer` enums, `DispatchMode::name`,
+/// `DispatchMode::saveable`, serde impls for `DispatchMode`, and the `MODE_REGISTRY`.
+/// A `DispatchMode` is saved as the name it's registered under and then the mode itself,
+/// so adding or reordering modes doesn't break old saves.
+///
+/// Each drawer type can only be listed once.
+macro_rules! register_modes {
+    (@saveable) => {
+        true
+    };
+    (@saveable $save:tt) => {
+        $save
+    };
+
+    (@serialize $tuple:ident, $mode:ident, $name:literal, false) => {
+        Err(serde::ser::Error::custom(concat!("Mode `", $name, "` can't be saved")))
+    };
+    (@serialize $tuple:ident, $mode:ident, $name:literal $(, true)?) => {{
+        serde::ser::SerializeTuple::serialize_element(&mut $tuple, $name)?;
+        serde::ser::SerializeTuple::serialize_element(&mut $tuple, $mode)?;
+        serde::ser::SerializeTuple::end($tuple)
+    }};
+
+    (@deserialize $seq:ident, $mode:ident, $name:literal, false) => {
+        Err(serde::de::Error::custom(concat!("Mode `", $name, "` can't be loaded")))
+    };
+    (@deserialize $seq:ident, $mode:ident, $name:literal $(, true)?) => {
+        $seq.next_element::<$mode>()?
+            .map(DispatchMode::from)
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &"a mode's name and the mode"))
+    };
+
+    ($(
+        $mode:ident {
+            name: $name:literal,
+            drawer: $drawer:ident,
+            new: $new:expr
+            $(, save: $save:tt)? $(,)?
+        }
+    ),* $(,)?) => {
+        // The doc attributes aren't just for show: enum_dispatch names the bindings in its
+        // generated `match`es after the span of each variant, and starting the variants with a token
+        // from inside this macro keeps those names hygienic.
+        #[enum_dispatch(Gamemode)]
+        pub enum DispatchMode {
+            $(
+                #[doc = concat!("Registered as `", $name, "`.")]
+                $mode,
+            )*
+        }
+
+        #[enum_dispatch(GamemodeDrawer)]
+        pub enum DispatchDrawer {
+            $(
+                #[doc = concat!("Drawer for `", stringify!($mode), "`.")]
+                $drawer,
+            )*
+        }
+
+        impl DispatchMode {
+            /// The name this mode was registered under.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(DispatchMode::$mode(_) => $name,)*
+                }
+            }
+
+            /// Can this mode go in a save file?
+            pub fn saveable(&self) -> bool {
+                match self {
+                    $(DispatchMode::$mode(_) => register_modes!(@saveable $($save)?),)*
+                }
+            }
+        }
+
+        impl serde::Serialize for DispatchMode {
+            // Modes that can't be saved don't use these
+            #[allow(unused_mut, unused_variables)]
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut tuple = serializer.serialize_tuple(2)?;
+                match self {
+                    $(DispatchMode::$mode(mode) => {
+                        register_modes!(@serialize tuple, mode, $name $(, $save)?)
+                    })*
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for DispatchMode {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct ModeVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for ModeVisitor {
+                    type Value = DispatchMode;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("a mode's name and the mode")
+                    }
+
+                    fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                        self,
+                        mut seq: A,
+                    ) -> Result<DispatchMode, A::Error> {
+                        let name: String = seq
+                            .next_element()?
+                            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                        match name.as_str() {
+                            $($name => register_modes!(@deserialize seq, $mode, $name $(, $save)?),)*
+                            other => Err(serde::de::Error::custom(format!(
+                                "No mode is registered as `{}`",
+                                other
+                            ))),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_tuple(2, ModeVisitor)
+            }
+        }
+
+        /// Every registered mode.
+        pub const MODE_REGISTRY: &[ModeEntry] = &[
+            $(ModeEntry {
+                name: $name,
+                new: {
+                    fn make(assets: &Assets) -> DispatchMode {
+                        #[allow(clippy::redundant_closure_call)]
+                        ($new)(assets).into()
+                    }
+                    make
+                },
+                saveable: register_modes!(@saveable $($save)?),
+            },)*
+        ];
+    };
+}
+
+register_modes! {
+    ModeLogo {
+        name: "logo",
+        drawer: ModeLogo,
+        new: |_| ModeLogo::new(),
+        save: false,
+    },
+    ModeExample {
+        name: "example",
+        drawer: ModeExample,
+        new: ModeExample::new,
+    },
+}
+
+/// How to make a registered mode.
+pub struct ModeEntry {
+    pub name: &'static str,
+    pub new: fn(&Assets) -> DispatchMode,
+    /// Can this mode go in a save file?
+    pub saveable: bool,
+}
+
+/// Find a mode by the name it was registered under.
+pub fn mode_entry(name: &str) -> Option<&'static ModeEntry> {
+    MODE_REGISTRY.iter().find(|entry| entry.name == name)
+}
+
+/// Make a mode from the name it was registered under, like for pushing it from a console command
+/// or a data file.
+pub fn new_mode_by_name(name: &str, assets: &Assets) -> Option<DispatchMode> {
+    mode_entry(name).map(|entry| (entry.new)(assets))
 }
 
-#[enum_dispatch(GamemodeDrawer)]
-pub enum DispatchDrawer {
-    ModeLogo,
-    ModeExample,
+/// The names of all the registered modes.
+pub fn mode_names() -> impl Iterator<Item = &'static str> {
+    MODE_REGISTRY.iter().map(|entry| entry.name)
 }
@@ -0,0 +1,84 @@
+//! Game-wide state that any mode can get at.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{clock::GameClock, events::EventBus, rng::GameRng};
+
+/// Holds one value of each type, for state shared between modes like settings,
+/// the current run, or the inventory.
+///
+/// The engine owns this and hands it to every `Gamemode::update`.
+/// Make a type for each resource so the types don't collide
+/// (store a `struct Score(u32)`, not a bare `u32`).
+#[derive(Default)]
+pub struct Resources {
+    map: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The engine's event bus.
+    pub fn events(&mut self) -> &mut EventBus {
+        self.get_or_default()
+    }
+
+    /// The engine's game clock.
+    pub fn clock(&mut self) -> &mut GameClock {
+        self.get_or_default()
+    }
+
+    /// The engine's seeded random number generator.
+    pub fn rng(&mut self) -> &mut GameRng {
+        self.get_or_default()
+    }
+
+    /// Put a resource in, returning the old one of that type if there was one.
+    pub fn insert<T: Any>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast().unwrap())
+    }
+
+    /// Take a resource out.
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .map(|old| *old.downcast().unwrap())
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .map(|value| value.downcast_ref().unwrap())
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .map(|value| value.downcast_mut().unwrap())
+    }
+
+    /// Get a resource, making it first if there isn't one yet.
+    pub fn get_or_insert_with<T: Any>(&mut self, make: impl FnOnce() -> T) -> &mut T {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(make()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Get a resource, making it with `Default` first if there isn't one yet.
+    pub fn get_or_default<T: Any + Default>(&mut self) -> &mut T {
+        self.get_or_insert_with(T::default)
+    }
+}
@@ -0,0 +1,79 @@
+//! Randomness that can be replayed.
+
+use macroquad::rand::compat::QuadRand;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// The engine's random number generator.
+///
+/// Use this for anything that affects the game, instead of `QuadRand` or `thread_rng`.
+/// It only changes when something draws from it, and the same seed always gives the
+/// same numbers, so recording the seed is enough to replay a run exactly.
+/// Get it with `Resources::rng`; it implements `rand::RngCore`, so all of `rand::Rng` works.
+///
+/// It's a SplitMix64 generator: tiny, fast, and the same on every platform,
+/// which `rand`'s own generators don't promise across versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Make one with a random seed.
+    pub fn from_entropy() -> Self {
+        Self::new(QuadRand.gen())
+    }
+
+    /// The seed this started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Start over from a new seed, like when starting a replay.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    /// Make a new generator from this one, for something that wants its own stream of numbers
+    /// (like a particle emitter) without its draws shifting everything else's.
+    pub fn fork(&mut self) -> GameRng {
+        GameRng::new(self.next_u64())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
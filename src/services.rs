@@ -0,0 +1,83 @@
+//! Systems that run every tick, no matter which mode is on top.
+
+use crate::{
+    assets::Assets,
+    boilerplates::{FrameInfo, GamemodeDrawer},
+    controls::InputSubscriber,
+    resources::Resources,
+};
+
+/// Something like a music manager, achievement tracker or debug overlay,
+/// that keeps going whatever the modes are doing.
+///
+/// Every tick, each service is updated before the mode stack, and its overlay is
+/// gathered after the mode stack and drawn on top of all the modes.
+pub trait Service {
+    /// Update the service. This happens before the mode stack updates.
+    #[allow(unused_variables)]
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+        resources: &mut Resources,
+    ) {
+    }
+
+    /// Gather information about how to draw this service's overlay, if it has one.
+    ///
+    /// Like `Gamemode::get_draw_info` this is called on the update thread,
+    /// so it hands back a drawer instead of drawing right away.
+    fn draw_overlay(&mut self) -> Option<Box<dyn GamemodeDrawer>> {
+        None
+    }
+}
+
+/// All the services the engine runs, in the order they were added.
+#[derive(Default)]
+pub struct Services {
+    services: Vec<Box<dyn Service>>,
+}
+
+impl Services {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a service. Services update and draw in the order they were added.
+    pub fn add(&mut self, service: impl Service + 'static) {
+        self.services.push(Box::new(service));
+    }
+
+    pub fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+        resources: &mut Resources,
+    ) {
+        for service in self.services.iter_mut() {
+            service.update(controls, frame_info, assets, resources);
+        }
+    }
+
+    pub fn draw_overlays(&mut self) -> ServiceOverlays {
+        ServiceOverlays(
+            self.services
+                .iter_mut()
+                .filter_map(|service| service.draw_overlay())
+                .collect(),
+        )
+    }
+}
+
+/// How to draw every service's overlay for one frame.
+pub struct ServiceOverlays(Vec<Box<dyn GamemodeDrawer>>);
+
+impl ServiceOverlays {
+    pub fn draw(&self, assets: &Assets, frame_info: FrameInfo) {
+        for overlay in self.0.iter() {
+            overlay.draw(assets, frame_info);
+        }
+    }
+}
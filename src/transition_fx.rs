@@ -0,0 +1,251 @@
+//! Animated effects for switching between modes.
+
+use cogs_gamedev::hash::hashcode;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Assets,
+    boilerplates::{FrameInfo, GamemodeDrawer},
+    controls::secs_to_ticks,
+    modes::DispatchDrawer,
+    utils::draw::canvas_camera,
+    HEIGHT, WIDTH,
+};
+
+use std::sync::Arc;
+
+/// Side length of the squares `TransitionEffect::Dissolve` fades in.
+const DISSOLVE_CELL_SIZE: f32 = 4.0;
+
+/// How the screen changes from the old modes to the new ones.
+#[derive(Debug, Copy, Clone)]
+pub enum TransitionEffect {
+    /// Fade the old modes out to this color, then fade the new ones in from it.
+    FadeToColor(Color),
+    /// Blend the old modes straight into the new ones.
+    Crossfade,
+    /// Push the new modes in over the old ones, moving in this direction.
+    Wipe(WipeDirection),
+    /// Reveal the new modes through a growing circle in the middle of the screen.
+    Iris,
+    /// Blocks of the new modes pop in one at a time.
+    Dissolve,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// An effect and how long it takes.
+#[derive(Debug, Copy, Clone)]
+pub struct TransitionFx {
+    pub effect: TransitionEffect,
+    /// How many update ticks the effect takes
+    pub ticks: u32,
+}
+
+impl TransitionFx {
+    /// Make an effect that lasts this many seconds, rounded to the nearest tick.
+    pub fn new(effect: TransitionEffect, secs: f32) -> Self {
+        Self {
+            effect,
+            ticks: secs_to_ticks(secs).max(1),
+        }
+    }
+}
+
+/// An effect that's playing right now.
+pub struct RunningEffect {
+    fx: TransitionFx,
+    /// What the modes that are going away looked like when the transition started.
+    outgoing: Arc<Vec<DispatchDrawer>>,
+    elapsed_ticks: u32,
+}
+
+impl RunningEffect {
+    pub fn new(fx: TransitionFx, outgoing: Vec<DispatchDrawer>) -> Self {
+        Self {
+            fx,
+            outgoing: Arc::new(outgoing),
+            elapsed_ticks: 0,
+        }
+    }
+
+    /// Advance one tick. Returns if the effect is done.
+    pub fn tick(&mut self) -> bool {
+        self.elapsed_ticks += 1;
+        self.elapsed_ticks >= self.fx.ticks
+    }
+
+    pub fn snapshot(&self) -> EffectSnapshot {
+        EffectSnapshot {
+            effect: self.fx.effect,
+            outgoing: Arc::clone(&self.outgoing),
+            progress: self.elapsed_ticks as f32 / self.fx.ticks as f32,
+        }
+    }
+}
+
+/// Everything needed to draw one frame of an effect.
+pub struct EffectSnapshot {
+    pub effect: TransitionEffect,
+    pub outgoing: Arc<Vec<DispatchDrawer>>,
+    /// From 0.0 at the start to 1.0 at the end
+    pub progress: f32,
+}
+
+/// Render targets to draw the old and new modes onto while an effect is playing.
+pub struct FxTargets {
+    pub canvas: RenderTarget,
+    pub outgoing: RenderTarget,
+    pub incoming: RenderTarget,
+}
+
+impl FxTargets {
+    /// Make the extra render targets. `canvas` is where the final picture goes.
+    pub fn new(canvas: RenderTarget) -> Self {
+        let make = || {
+            let target = render_target(WIDTH as u32, HEIGHT as u32);
+            target.texture.set_filter(FilterMode::Nearest);
+            target
+        };
+        Self {
+            canvas,
+            outgoing: make(),
+            incoming: make(),
+        }
+    }
+}
+
+impl EffectSnapshot {
+    /// Draw the effect onto the canvas, with `incoming` being the drawers for the new modes.
+    ///
+    /// This switches cameras around, and leaves the canvas camera set when it's done.
+    pub fn draw(
+        &self,
+        incoming: &[DispatchDrawer],
+        targets: &FxTargets,
+        assets: &Assets,
+        frame_info: FrameInfo,
+    ) {
+        let p = self.progress.clamp(0.0, 1.0);
+
+        render_drawers(&self.outgoing, targets.outgoing, assets, frame_info);
+        render_drawers(incoming, targets.incoming, assets, frame_info);
+        set_camera(&canvas_camera(targets.canvas));
+
+        let old = targets.outgoing.texture;
+        let new = targets.incoming.texture;
+        match self.effect {
+            TransitionEffect::FadeToColor(color) => {
+                // first half fades out, second half fades in
+                let (tex, alpha) = if p < 0.5 {
+                    (old, p * 2.0)
+                } else {
+                    (new, (1.0 - p) * 2.0)
+                };
+                draw_texture(tex, 0.0, 0.0, WHITE);
+                draw_rectangle(0.0, 0.0, WIDTH, HEIGHT, Color { a: alpha, ..color });
+            }
+            TransitionEffect::Crossfade => {
+                draw_texture(new, 0.0, 0.0, WHITE);
+                draw_texture(old, 0.0, 0.0, Color::new(1.0, 1.0, 1.0, 1.0 - p));
+            }
+            TransitionEffect::Wipe(dir) => {
+                draw_texture(old, 0.0, 0.0, WHITE);
+                // the part of the screen the new modes cover
+                let covered = match dir {
+                    WipeDirection::Right => Rect::new(0.0, 0.0, WIDTH * p, HEIGHT),
+                    WipeDirection::Left => Rect::new(WIDTH * (1.0 - p), 0.0, WIDTH * p, HEIGHT),
+                    WipeDirection::Down => Rect::new(0.0, 0.0, WIDTH, HEIGHT * p),
+                    WipeDirection::Up => Rect::new(0.0, HEIGHT * (1.0 - p), WIDTH, HEIGHT * p),
+                };
+                draw_region(new, round_rect(covered));
+            }
+            TransitionEffect::Iris => {
+                draw_texture(old, 0.0, 0.0, WHITE);
+                // Big enough to cover the corners at the end
+                let max_radius = vec2(WIDTH, HEIGHT).length() / 2.0;
+                let radius = max_radius * p;
+                // Draw the circle one row at a time so it stays pixelly
+                for row in 0..HEIGHT as u32 {
+                    let dy = row as f32 + 0.5 - HEIGHT / 2.0;
+                    let half_chord_sq = radius * radius - dy * dy;
+                    if half_chord_sq <= 0.0 {
+                        continue;
+                    }
+                    let half_chord = half_chord_sq.sqrt();
+                    let left = (WIDTH / 2.0 - half_chord).round().max(0.0);
+                    let right = (WIDTH / 2.0 + half_chord).round().min(WIDTH);
+                    draw_region(new, Rect::new(left, row as f32, right - left, 1.0));
+                }
+            }
+            TransitionEffect::Dissolve => {
+                draw_texture(old, 0.0, 0.0, WHITE);
+                let cols = (WIDTH / DISSOLVE_CELL_SIZE).ceil() as u32;
+                let rows = (HEIGHT / DISSOLVE_CELL_SIZE).ceil() as u32;
+                for cy in 0..rows {
+                    for cx in 0..cols {
+                        // Each cell gets a fixed pseudorandom time to appear at
+                        let threshold = (hashcode(&(cx, cy)) % 1024) as f32 / 1024.0;
+                        if threshold < p {
+                            draw_region(
+                                new,
+                                Rect::new(
+                                    cx as f32 * DISSOLVE_CELL_SIZE,
+                                    cy as f32 * DISSOLVE_CELL_SIZE,
+                                    DISSOLVE_CELL_SIZE,
+                                    DISSOLVE_CELL_SIZE,
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draw the drawers onto a render target from scratch.
+fn render_drawers(
+    drawers: &[DispatchDrawer],
+    target: RenderTarget,
+    assets: &Assets,
+    frame_info: FrameInfo,
+) {
+    set_camera(&canvas_camera(target));
+    clear_background(WHITE);
+    for drawer in drawers {
+        drawer.draw(assets, frame_info);
+    }
+}
+
+/// Copy a region of a canvas-sized texture to the same place on the canvas.
+fn draw_region(tex: Texture2D, region: Rect) {
+    if region.w <= 0.0 || region.h <= 0.0 {
+        return;
+    }
+    draw_texture_ex(
+        tex,
+        region.x,
+        region.y,
+        WHITE,
+        DrawTextureParams {
+            source: Some(region),
+            ..Default::default()
+        },
+    );
+}
+
+fn round_rect(rect: Rect) -> Rect {
+    Rect::new(
+        rect.x.round(),
+        rect.y.round(),
+        rect.w.round(),
+        rect.h.round(),
+    )
+}
@@ -1,17 +1,16 @@
 use macroquad::prelude::*;
 
-use crate::assets::Assets;
+use crate::{assets::Assets, controls::InputSubscriber};
 
-use super::{
-    draw::mouse_position_pixel,
-    text::{draw_pixel_text, TextAlign},
-};
+use super::text::{draw_pixel_text, TextAlign};
 
 /// Button to be pressed
 #[derive(Debug, Clone)]
 pub struct Button {
     pub bounds: Rect,
     text: Option<(String, TextAlign)>,
+    /// Is the mouse on here this frame?
+    is_mouse_hovering: bool,
     /// Was the mouse on here last frame?
     was_mouse_hovering: bool,
 }
@@ -20,6 +19,7 @@ impl Button {
     pub fn new_from_rect(bounds: Rect, text: Option<(String, TextAlign)>) -> Self {
         Self {
             bounds,
+            is_mouse_hovering: false,
             was_mouse_hovering: false,
             text,
         }
@@ -46,16 +46,20 @@ impl Button {
         self.bounds.h
     }
 
-    /// You must call this at the *end* of every frame, after all
-    /// processing is done.
-    pub fn post_update(&mut self) {
-        self.was_mouse_hovering = self.mouse_hovering();
+    /// You must call this at the *start* of every frame, before checking
+    /// anything about the mouse.
+    ///
+    /// This replaces the old `post_update`, which had to be called at the end of the frame
+    /// and read the global mouse position. Reading it from `controls` instead means
+    /// the button agrees with everything else the mode sees that tick.
+    pub fn update(&mut self, controls: &InputSubscriber) {
+        self.was_mouse_hovering = self.is_mouse_hovering;
+        self.is_mouse_hovering = self.bounds.contains(controls.mouse_pos());
     }
 
     /// Is the mouse currently over this?
     pub fn mouse_hovering(&self) -> bool {
-        let (mx, my) = mouse_position_pixel();
-        self.bounds.contains(vec2(mx, my))
+        self.is_mouse_hovering
     }
 
     /// Did the mouse enter the button this frame?
@@ -0,0 +1,257 @@
+//! A camera for looking around a world bigger than the canvas.
+//!
+//! The engine always draws onto the canvas with a fixed camera, one unit per canvas pixel.
+//! A `GameCamera` goes on top of that: it moves the world around underneath the canvas,
+//! so a mode only has to draw things at their world positions.
+//!
+//! ```ignore
+//! // In update
+//! self.camera.target = Some(self.player.pos);
+//! self.camera.update(frame_info);
+//!
+//! // In draw
+//! self.camera.draw_with(|| {
+//!     draw_texture(assets.textures.player, self.player.pos.x, self.player.pos.y, WHITE);
+//! });
+//! ```
+
+use cogs_gamedev::hash::hashcode;
+use macroquad::prelude::*;
+
+use crate::{
+    boilerplates::FrameInfo,
+    utils::draw::{mouse_position_pixel, width_height_deficit_of, window_to_pixel},
+    HEIGHT, WIDTH,
+};
+
+/// A camera that a mode owns and updates itself.
+///
+/// It's `Copy`, so the drawer can just take a copy of it every frame.
+#[derive(Debug, Clone, Copy)]
+pub struct GameCamera {
+    /// The point in the world at the center of the canvas
+    pub center: Vec2,
+    /// What to follow, if anything
+    pub target: Option<Vec2>,
+    /// Half the size of the box around the center that the target can move around in
+    /// without the camera following it
+    pub deadzone: Vec2,
+    /// Roughly how many seconds it takes to catch up with the target.
+    /// Zero sticks right to it.
+    pub smoothing: f32,
+    /// The area of the world to stay inside, so the edges of the world never scroll into view.
+    /// If it's smaller than the view, the view is centered on it.
+    pub bounds: Option<Rect>,
+    /// How far the screen shakes at full trauma, in canvas pixels
+    pub max_shake: f32,
+    /// How much trauma goes away per second
+    pub trauma_decay: f32,
+
+    /// How many canvas pixels one world unit takes up
+    zoom: u32,
+    /// How shaken up the camera is, from 0.0 to 1.0
+    trauma: f32,
+    /// This tick's shake, in whole canvas pixels
+    shake: Vec2,
+    /// How many ticks the camera has moved for, to seed the shake
+    ticks: u64,
+}
+
+impl GameCamera {
+    /// Make a camera looking at this point in the world.
+    pub fn new(center: Vec2) -> Self {
+        Self {
+            center,
+            target: None,
+            deadzone: Vec2::ZERO,
+            smoothing: 0.0,
+            bounds: None,
+            max_shake: 8.0,
+            trauma_decay: 1.5,
+            zoom: 1,
+            trauma: 0.0,
+            shake: Vec2::ZERO,
+            ticks: 0,
+        }
+    }
+
+    pub fn zoom(&self) -> u32 {
+        self.zoom
+    }
+
+    /// Set how many canvas pixels one world unit takes up.
+    /// It's always a whole number so pixel art stays crisp. Zero is treated as one.
+    pub fn set_zoom(&mut self, zoom: u32) {
+        self.zoom = zoom.max(1);
+        self.clamp_to_bounds();
+    }
+
+    /// Shake the screen. Trauma adds up to 1.0 and wears off over time;
+    /// the shake goes with the square of it, so small hits stay small.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Jump straight to a point without smoothing, like when a level starts.
+    pub fn snap_to(&mut self, center: Vec2) {
+        self.center = center;
+        self.clamp_to_bounds();
+    }
+
+    /// Follow the target and shake. Call this once per tick with the mode's `FrameInfo`.
+    ///
+    /// The camera only moves with game time, so it holds still while the game is paused.
+    pub fn update(&mut self, frame_info: FrameInfo) {
+        if frame_info.dt <= 0.0 {
+            return;
+        }
+
+        if let Some(target) = self.target {
+            // Where the camera needs to be to get the target back inside the deadzone
+            let mut wanted = self.center;
+            let offset = target - self.center;
+            if offset.x > self.deadzone.x {
+                wanted.x = target.x - self.deadzone.x;
+            } else if offset.x < -self.deadzone.x {
+                wanted.x = target.x + self.deadzone.x;
+            }
+            if offset.y > self.deadzone.y {
+                wanted.y = target.y - self.deadzone.y;
+            } else if offset.y < -self.deadzone.y {
+                wanted.y = target.y + self.deadzone.y;
+            }
+
+            let t = if self.smoothing <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-frame_info.dt / self.smoothing).exp()
+            };
+            self.center += (wanted - self.center) * t;
+        }
+        self.clamp_to_bounds();
+
+        self.ticks += 1;
+        self.trauma = (self.trauma - self.trauma_decay * frame_info.dt).max(0.0);
+        let amount = self.max_shake * self.trauma * self.trauma;
+        // Hash the tick instead of using an rng so replays shake the same way
+        let noise = |axis: u8| (hashcode(&(self.ticks, axis)) % 2001) as f32 / 1000.0 - 1.0;
+        self.shake = vec2(noise(0) * amount, noise(1) * amount).round();
+    }
+
+    fn clamp_to_bounds(&mut self) {
+        if let Some(bounds) = self.bounds {
+            let half = self.view_size() / 2.0;
+            self.center.x = if bounds.w <= half.x * 2.0 {
+                bounds.x + bounds.w / 2.0
+            } else {
+                self.center
+                    .x
+                    .clamp(bounds.x + half.x, bounds.right() - half.x)
+            };
+            self.center.y = if bounds.h <= half.y * 2.0 {
+                bounds.y + bounds.h / 2.0
+            } else {
+                self.center
+                    .y
+                    .clamp(bounds.y + half.y, bounds.bottom() - half.y)
+            };
+        }
+    }
+
+    /// How much of the world fits on the canvas.
+    pub fn view_size(&self) -> Vec2 {
+        vec2(WIDTH, HEIGHT) / self.zoom as f32
+    }
+
+    /// The area of the world on the canvas right now, not counting shake.
+    /// Handy for skipping things that are offscreen.
+    pub fn view_rect(&self) -> Rect {
+        let size = self.view_size();
+        let corner = self.center - size / 2.0;
+        Rect::new(corner.x, corner.y, size.x, size.y)
+    }
+
+    /// Where on the canvas the world's origin ends up, in whole canvas pixels.
+    ///
+    /// Rounding this is what keeps sprites from shimmering as the camera moves slowly:
+    /// everything moves by whole pixels together.
+    fn offset(&self) -> Vec2 {
+        let zoom = self.zoom as f32;
+        (vec2(WIDTH, HEIGHT) / 2.0 - self.center * zoom).round() + self.shake
+    }
+
+    pub fn world_to_canvas(&self, world: Vec2) -> Vec2 {
+        world * self.zoom as f32 + self.offset()
+    }
+
+    pub fn canvas_to_world(&self, canvas: Vec2) -> Vec2 {
+        (canvas - self.offset()) / self.zoom as f32
+    }
+
+    /// Convert a position on a window of the given size to a position in the world.
+    pub fn window_to_world(&self, window: Vec2, screen_width: f32, screen_height: f32) -> Vec2 {
+        let (x, y) = window_to_pixel(window.x, window.y, screen_width, screen_height);
+        self.canvas_to_world(vec2(x, y))
+    }
+
+    /// Convert a position in the world to a position on a window of the given size.
+    pub fn world_to_window(&self, world: Vec2, screen_width: f32, screen_height: f32) -> Vec2 {
+        let canvas = self.world_to_canvas(world);
+        let (wd, hd) = width_height_deficit_of(screen_width, screen_height);
+        vec2(
+            canvas.x * ((screen_width - wd) / WIDTH) + wd / 2.0,
+            canvas.y * ((screen_height - hd) / HEIGHT) + hd / 2.0,
+        )
+    }
+
+    /// Get the mouse position in the world straight from macroquad.
+    ///
+    /// Inside `Gamemode::update` prefer `canvas_to_world(controls.mouse_pos())`.
+    pub fn mouse_world(&self) -> Vec2 {
+        let (x, y) = mouse_position_pixel();
+        self.canvas_to_world(vec2(x, y))
+    }
+
+    /// Draw things at their world positions.
+    ///
+    /// This goes on top of whatever camera is set, so it works on the canvas and
+    /// during transition effects alike.
+    pub fn draw_with<R>(&self, draw: impl FnOnce() -> R) -> R {
+        let zoom = self.zoom as f32;
+        let offset = self.offset();
+        let matrix = Mat4::from_translation(vec3(offset.x, offset.y, 0.0))
+            * Mat4::from_scale(vec3(zoom, zoom, 1.0));
+
+        /// Pops the matrix again once drawing's done, even if `draw` panics,
+        /// so a panic can't leave everything after it drawn through this camera.
+        struct PopMatrix;
+        impl Drop for PopMatrix {
+            fn drop(&mut self) {
+                // SAFETY: see below; this pops the matrix pushed there, on the same thread.
+                unsafe {
+                    get_internal_gl().quad_gl.pop_model_matrix();
+                }
+            }
+        }
+
+        // SAFETY: `get_internal_gl` is only sound on the main thread while nothing else
+        // is holding onto the gl. Drawers only run on the main thread, and the gl is only
+        // borrowed for the length of the push. Every push has exactly one pop, from `PopMatrix`,
+        // so the model matrix stack is always back how it was once this returns or unwinds.
+        unsafe {
+            get_internal_gl().quad_gl.push_model_matrix(matrix);
+        }
+        let _pop = PopMatrix;
+        draw()
+    }
+}
+
+impl Default for GameCamera {
+    fn default() -> Self {
+        Self::new(vec2(WIDTH / 2.0, HEIGHT / 2.0))
+    }
+}
@@ -0,0 +1,213 @@
+//! Axis-aligned boxes and how they bump into things.
+//!
+//! - `Aabb` has overlap tests, pushing boxes apart, and sweeping one box into another.
+//! - `tiles` moves a box through a grid of tiles, with slopes and one-way platforms.
+//! - `SpatialHash` finds which boxes might be touching without checking every pair.
+//!
+//! Everything is in world units (map pixels), with Y down like the rest of the engine.
+//! Normals point out of whatever was hit, so a box landing on the floor
+//! gets a normal of `(0, -1)`.
+
+mod spatial_hash;
+pub mod tiles;
+
+pub use spatial_hash::SpatialHash;
+
+use macroquad::prelude::*;
+
+/// An axis-aligned box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// The top-left corner
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+/// Where a box got pushed out of something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// Points out of the thing that was hit
+    pub normal: Vec2,
+    /// How far the box was pushed along the normal
+    pub depth: f32,
+}
+
+/// Where a moving box first touches something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// How far along the movement it touched, from 0.0 to 1.0
+    pub time: f32,
+    /// Points out of the thing that was hit
+    pub normal: Vec2,
+}
+
+impl Aabb {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self {
+            pos: vec2(x, y),
+            size: vec2(w, h),
+        }
+    }
+
+    pub fn from_center(center: Vec2, size: Vec2) -> Self {
+        Self {
+            pos: center - size / 2.0,
+            size,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size / 2.0
+    }
+
+    pub fn left(&self) -> f32 {
+        self.pos.x
+    }
+
+    pub fn right(&self) -> f32 {
+        self.pos.x + self.size.x
+    }
+
+    pub fn top(&self) -> f32 {
+        self.pos.y
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.pos.y + self.size.y
+    }
+
+    pub fn translated(&self, by: Vec2) -> Self {
+        Self {
+            pos: self.pos + by,
+            size: self.size,
+        }
+    }
+
+    /// Do these overlap? Boxes that only share an edge don't.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.left() < other.right()
+            && other.left() < self.right()
+            && self.top() < other.bottom()
+            && other.top() < self.bottom()
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.left()
+            && point.x < self.right()
+            && point.y >= self.top()
+            && point.y < self.bottom()
+    }
+
+    /// The shortest way to push this box out of `other`, if they overlap.
+    pub fn penetration(&self, other: &Aabb) -> Option<Contact> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        // How far to push in each direction to get out
+        let push_left = self.right() - other.left();
+        let push_right = other.right() - self.left();
+        let push_up = self.bottom() - other.top();
+        let push_down = other.bottom() - self.top();
+
+        let (x_normal, x_depth) = if push_left < push_right {
+            (vec2(-1.0, 0.0), push_left)
+        } else {
+            (vec2(1.0, 0.0), push_right)
+        };
+        let (y_normal, y_depth) = if push_up < push_down {
+            (vec2(0.0, -1.0), push_up)
+        } else {
+            (vec2(0.0, 1.0), push_down)
+        };
+        Some(if x_depth < y_depth {
+            Contact {
+                normal: x_normal,
+                depth: x_depth,
+            }
+        } else {
+            Contact {
+                normal: y_normal,
+                depth: y_depth,
+            }
+        })
+    }
+
+    /// Push this box out of `other`, returning how it was pushed if they overlapped.
+    pub fn resolve(&mut self, other: &Aabb) -> Option<Contact> {
+        let contact = self.penetration(other)?;
+        self.pos += contact.normal * contact.depth;
+        Some(contact)
+    }
+
+    /// Push two movable boxes out of each other, each going half the way.
+    /// The contact is from `a`'s point of view.
+    pub fn resolve_both(a: &mut Aabb, b: &mut Aabb) -> Option<Contact> {
+        let contact = a.penetration(b)?;
+        a.pos += contact.normal * contact.depth / 2.0;
+        b.pos -= contact.normal * contact.depth / 2.0;
+        Some(contact)
+    }
+
+    /// Where this box first touches `other` if it moves by `delta`.
+    ///
+    /// Boxes that already overlap don't hit; use `resolve` for those.
+    pub fn sweep(&self, delta: Vec2, other: &Aabb) -> Option<Hit> {
+        // Grow the other box by this one, then this is just a ray from the corner
+        let expanded = Aabb {
+            pos: other.pos - self.size,
+            size: other.size + self.size,
+        };
+        if expanded.overlaps(&Aabb {
+            pos: self.pos,
+            size: Vec2::ZERO,
+        }) {
+            return None;
+        }
+
+        let (x_entry, x_exit) = slab(self.pos.x, delta.x, expanded.left(), expanded.right());
+        let (y_entry, y_exit) = slab(self.pos.y, delta.y, expanded.top(), expanded.bottom());
+        let entry = x_entry.max(y_entry);
+        let exit = x_exit.min(y_exit);
+        if entry > exit || !(0.0..=1.0).contains(&entry) {
+            return None;
+        }
+
+        let normal = if x_entry > y_entry {
+            vec2(-delta.x.signum(), 0.0)
+        } else {
+            vec2(0.0, -delta.y.signum())
+        };
+        Some(Hit {
+            time: entry,
+            normal,
+        })
+    }
+}
+
+/// When a point moving by `delta` enters and leaves the range from `lo` to `hi`,
+/// as fractions of the movement.
+fn slab(start: f32, delta: f32, lo: f32, hi: f32) -> (f32, f32) {
+    if delta == 0.0 {
+        if start > lo && start < hi {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        }
+    } else {
+        let a = (lo - start) / delta;
+        let b = (hi - start) / delta;
+        (a.min(b), a.max(b))
+    }
+}
+
+impl From<Rect> for Aabb {
+    fn from(rect: Rect) -> Self {
+        Aabb::new(rect.x, rect.y, rect.w, rect.h)
+    }
+}
+
+impl From<Aabb> for Rect {
+    fn from(aabb: Aabb) -> Self {
+        Rect::new(aabb.pos.x, aabb.pos.y, aabb.size.x, aabb.size.y)
+    }
+}
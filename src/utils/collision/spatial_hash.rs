@@ -0,0 +1,128 @@
+use macroquad::prelude::Vec2;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::Hash,
+};
+
+use super::Aabb;
+
+/// Buckets boxes by which grid cells they touch, to quickly find the ones that
+/// might be touching something.
+///
+/// `K` is whatever names the things in it, like an index or an entity ID.
+/// Queries return keys in sorted order, so results don't depend on hashing and
+/// replays stay in sync.
+#[derive(Debug, Clone)]
+pub struct SpatialHash<K> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<K>>,
+    boxes: HashMap<K, Aabb>,
+}
+
+impl<K: Copy + Ord + Hash> SpatialHash<K> {
+    /// Make an empty one. Cells about the size of the things in it work best.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            boxes: HashMap::new(),
+        }
+    }
+
+    /// Put a box in, replacing the old box for that key if there was one.
+    pub fn insert(&mut self, key: K, aabb: Aabb) {
+        self.remove(key);
+        for cell in self.cells_of(&aabb) {
+            self.cells.entry(cell).or_default().push(key);
+        }
+        self.boxes.insert(key, aabb);
+    }
+
+    /// Take a box out, returning it if it was in there.
+    pub fn remove(&mut self, key: K) -> Option<Aabb> {
+        let aabb = self.boxes.remove(&key)?;
+        for cell in self.cells_of(&aabb) {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|k| *k != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        Some(aabb)
+    }
+
+    pub fn get(&self, key: K) -> Option<Aabb> {
+        self.boxes.get(&key).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.boxes.clear();
+    }
+
+    /// Everything overlapping the box, in key order.
+    pub fn query(&self, aabb: &Aabb) -> Vec<K> {
+        let mut found = BTreeSet::new();
+        for cell in self.cells_of(aabb) {
+            if let Some(keys) = self.cells.get(&cell) {
+                for &key in keys {
+                    if self.boxes[&key].overlaps(aabb) {
+                        found.insert(key);
+                    }
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// Everything containing the point, in key order.
+    pub fn query_point(&self, point: Vec2) -> Vec<K> {
+        let cell = (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        );
+        let mut found: Vec<K> = match self.cells.get(&cell) {
+            Some(keys) => keys
+                .iter()
+                .copied()
+                .filter(|key| self.boxes[key].contains_point(point))
+                .collect(),
+            None => Vec::new(),
+        };
+        found.sort_unstable();
+        found
+    }
+
+    /// Every pair of boxes that overlap, each once with the smaller key first, in order.
+    pub fn pairs(&self) -> Vec<(K, K)> {
+        let mut found = BTreeSet::new();
+        for keys in self.cells.values() {
+            for (i, &a) in keys.iter().enumerate() {
+                for &b in &keys[i + 1..] {
+                    if self.boxes[&a].overlaps(&self.boxes[&b]) {
+                        found.insert((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    fn cells_of(&self, aabb: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+        let x0 = (aabb.left() / self.cell_size).floor() as i32;
+        let y0 = (aabb.top() / self.cell_size).floor() as i32;
+        let x1 = (aabb.right() / self.cell_size).floor() as i32;
+        let y1 = (aabb.bottom() / self.cell_size).floor() as i32;
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+    }
+}
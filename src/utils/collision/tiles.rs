@@ -0,0 +1,611 @@
+//! Moving boxes through a grid of tiles.
+//!
+//! Tiles are solid, one-way platforms you can jump up through, or floor slopes.
+//! `move_aabb` moves a box one axis at a time, stopping at the first tile in the way
+//! on each axis, so it never tunnels through thin walls however fast it goes.
+
+use macroquad::prelude::*;
+
+use super::Aabb;
+use crate::utils::tilemap::{Properties, Tilemap};
+
+/// A little bit of slack for floating point edges.
+const EPSILON: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileShape {
+    Empty,
+    Solid,
+    /// Only solid from above, and only to things that were above it already
+    OneWay,
+    /// A floor slope, solid below the line between the heights at its left and right edges.
+    /// Heights go from 0.0 at the bottom of the tile to 1.0 at the top.
+    Slope {
+        left: f32,
+        right: f32,
+    },
+}
+
+impl TileShape {
+    /// A 45 degree slope going up to the right
+    pub const SLOPE_UP: TileShape = TileShape::Slope {
+        left: 0.0,
+        right: 1.0,
+    };
+    /// A 45 degree slope going down to the right
+    pub const SLOPE_DOWN: TileShape = TileShape::Slope {
+        left: 1.0,
+        right: 0.0,
+    };
+
+    /// Read a tile's shape from its Tiled properties.
+    ///
+    /// The string property `collision` can be `solid`, `oneway`, `slope_up`, `slope_down`,
+    /// or `slope`, in which case the float properties `slope_left` and `slope_right` give the heights.
+    /// Anything else, or no property at all, is empty.
+    pub fn from_properties(properties: Option<&Properties>) -> TileShape {
+        let properties = match properties {
+            Some(it) => it,
+            None => return TileShape::Empty,
+        };
+        match properties.str("collision") {
+            Some("solid") => TileShape::Solid,
+            Some("oneway") => TileShape::OneWay,
+            Some("slope_up") => TileShape::SLOPE_UP,
+            Some("slope_down") => TileShape::SLOPE_DOWN,
+            Some("slope") => TileShape::Slope {
+                left: properties.float("slope_left").unwrap_or(0.0) as f32,
+                right: properties.float("slope_right").unwrap_or(0.0) as f32,
+            },
+            _ => TileShape::Empty,
+        }
+    }
+
+    /// How high the floor is this far across the tile, if it's a slope.
+    fn slope_height(&self, across: f32) -> Option<f32> {
+        match *self {
+            TileShape::Slope { left, right } => {
+                let across = across.clamp(0.0, 1.0);
+                Some(left + (right - left) * across)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Anything that's a grid of tile shapes.
+pub trait TileCollider {
+    /// Size of one tile in world units
+    fn tile_size(&self) -> Vec2;
+    /// The shape of the tile at these coordinates. Can be asked about tiles off the grid.
+    fn shape_at(&self, x: i32, y: i32) -> TileShape;
+}
+
+/// A plain grid of tile shapes.
+#[derive(Debug, Clone)]
+pub struct CollisionGrid {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: Vec2,
+    /// What everything off the grid is
+    pub outside: TileShape,
+    shapes: Vec<TileShape>,
+}
+
+impl CollisionGrid {
+    /// Make an empty grid with solid outside it.
+    pub fn new(width: u32, height: u32, tile_size: Vec2) -> Self {
+        Self {
+            width,
+            height,
+            tile_size,
+            outside: TileShape::Solid,
+            shapes: vec![TileShape::Empty; (width * height) as usize],
+        }
+    }
+
+    /// Make a grid out of one of a map's tile layers, with each tile's shape
+    /// from its properties like in `TileShape::from_properties`.
+    pub fn from_tilemap(map: &Tilemap, layer: &str) -> Option<Self> {
+        Self::from_tilemap_with(map, layer, |gid| {
+            TileShape::from_properties(map.tile_properties(gid))
+        })
+    }
+
+    /// Make a grid out of one of a map's tile layers, deciding the shape from the global tile ID.
+    pub fn from_tilemap_with(
+        map: &Tilemap,
+        layer: &str,
+        mut shape_of: impl FnMut(u32) -> TileShape,
+    ) -> Option<Self> {
+        let tiles = map.tile_layer(layer)?;
+        let mut grid = CollisionGrid::new(
+            tiles.width,
+            tiles.height,
+            vec2(map.tile_width as f32, map.tile_height as f32),
+        );
+        for y in 0..tiles.height as i32 {
+            for x in 0..tiles.width as i32 {
+                match tiles.get(x, y) {
+                    Some(0) | None => {}
+                    Some(gid) => grid.set(x, y, shape_of(gid)),
+                }
+            }
+        }
+        Some(grid)
+    }
+
+    /// Change a tile. Does nothing off the grid.
+    pub fn set(&mut self, x: i32, y: i32, shape: TileShape) {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            self.shapes[(y as u32 * self.width + x as u32) as usize] = shape;
+        }
+    }
+}
+
+impl TileCollider for CollisionGrid {
+    fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    fn shape_at(&self, x: i32, y: i32) -> TileShape {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            self.shapes[(y as u32 * self.width + x as u32) as usize]
+        } else {
+            self.outside
+        }
+    }
+}
+
+/// A tile a box bumped into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileContact {
+    /// Points out of the tile
+    pub normal: Vec2,
+    pub tile: (i32, i32),
+}
+
+/// What happened when moving a box.
+#[derive(Debug, Clone)]
+pub struct Movement {
+    /// Where the box ended up
+    pub aabb: Aabb,
+    /// How far it actually went
+    pub moved: Vec2,
+    /// Everything it bumped into, in the order it happened
+    pub contacts: Vec<TileContact>,
+}
+
+impl Movement {
+    /// Is the box standing on something? True if anything pushed it upwards,
+    /// including slopes.
+    pub fn on_ground(&self) -> bool {
+        self.contacts.iter().any(|contact| contact.normal.y < 0.0)
+    }
+
+    /// Did it hit its head?
+    pub fn on_ceiling(&self) -> bool {
+        self.contacts.iter().any(|contact| contact.normal.y > 0.0)
+    }
+
+    /// Did it hit a wall? Gives the wall's normal.
+    pub fn on_wall(&self) -> Option<Vec2> {
+        self.contacts
+            .iter()
+            .find(|contact| contact.normal.y == 0.0)
+            .map(|contact| contact.normal)
+    }
+}
+
+/// Move a box by `delta` through the tiles, stopping at whatever's in the way.
+///
+/// It moves across first, then up or down, then settles onto any slope it's on.
+/// Zero out the velocity along any contact normal you get back.
+pub fn move_aabb(tiles: &impl TileCollider, aabb: Aabb, delta: Vec2) -> Movement {
+    let size = tiles.tile_size();
+    let start = aabb;
+    let mut aabb = aabb;
+    let mut contacts = Vec::new();
+
+    // Across
+    if delta.x != 0.0 {
+        // Boxes can step up a little onto slopes, and off the top of a slope onto the ground
+        // past it, but not onto anything else
+        let max_step = size.y / 2.0;
+        let back = if delta.x > 0.0 { -1 } else { 1 };
+        let (rows_lo, rows_hi) = span(aabb.top(), aabb.bottom(), size.y);
+        let target = if delta.x > 0.0 {
+            aabb.right() + delta.x
+        } else {
+            aabb.left() + delta.x
+        };
+        let cols: Box<dyn Iterator<Item = i32>> = if delta.x > 0.0 {
+            Box::new(ceil_div(aabb.right(), size.x)..=ceil_div(target, size.x) - 1)
+        } else {
+            Box::new((floor_div(target, size.x)..=floor_div(aabb.left(), size.x) - 1).rev())
+        };
+
+        let mut blocked = None;
+        // The highest ground low enough to step up onto, and the tile it's on
+        let mut step_onto: Option<(f32, (i32, i32))> = None;
+        'cols: for col in cols {
+            let mut col_step = None;
+            for row in rows_lo..=rows_hi {
+                // How high the tile is where the box walks into it, and how far it can step up it
+                let (top, step) = match tiles.shape_at(col, row) {
+                    TileShape::Solid => {
+                        let step = match tiles.shape_at(col + back, row) {
+                            TileShape::Slope { .. } => max_step,
+                            _ => 0.0,
+                        };
+                        (row as f32 * size.y, step)
+                    }
+                    // The tall side of a slope is a wall
+                    TileShape::Slope { left, right } => {
+                        let side = if delta.x > 0.0 { left } else { right };
+                        ((row as f32 + 1.0 - side) * size.y, max_step)
+                    }
+                    TileShape::Empty | TileShape::OneWay => continue,
+                };
+                if col_step.is_none()
+                    && top >= aabb.bottom() - step
+                    && top < aabb.bottom() - EPSILON
+                {
+                    col_step = Some((top, (col, row)));
+                }
+                if top < aabb.bottom() - step - EPSILON {
+                    blocked = Some((col, row));
+                    break 'cols;
+                }
+            }
+            // Rows go top to bottom, so that's the highest step in the column
+            if let Some((top, tile)) = col_step {
+                match step_onto {
+                    Some((highest, _)) if highest <= top => {}
+                    _ => step_onto = Some((top, tile)),
+                }
+            }
+        }
+
+        match blocked {
+            Some((col, row)) => {
+                if delta.x > 0.0 {
+                    aabb.pos.x = col as f32 * size.x - aabb.size.x;
+                    contacts.push(TileContact {
+                        normal: vec2(-1.0, 0.0),
+                        tile: (col, row),
+                    });
+                } else {
+                    aabb.pos.x = (col + 1) as f32 * size.x;
+                    contacts.push(TileContact {
+                        normal: vec2(1.0, 0.0),
+                        tile: (col, row),
+                    });
+                }
+            }
+            None => aabb.pos.x += delta.x,
+        }
+
+        // Step up onto whatever the box walked into, wherever it is under the box,
+        // unless that would bump its head
+        if let Some((top, (col, row))) = step_onto {
+            let lifted = aabb.translated(vec2(0.0, top - aabb.bottom()));
+            let (cols_lo, cols_hi) = span(lifted.left(), lifted.right(), size.x);
+            let (head_lo, _) = span(lifted.top(), lifted.bottom(), size.y);
+            let bumps_head = (head_lo..rows_lo).any(|row| {
+                (cols_lo..=cols_hi).any(|col| {
+                    matches!(
+                        tiles.shape_at(col, row),
+                        TileShape::Solid | TileShape::Slope { .. }
+                    )
+                })
+            });
+            if bumps_head {
+                // Too low to fit through, so it's a wall after all
+                if delta.x > 0.0 {
+                    aabb.pos.x = col as f32 * size.x - aabb.size.x;
+                    contacts.push(TileContact {
+                        normal: vec2(-1.0, 0.0),
+                        tile: (col, row),
+                    });
+                } else {
+                    aabb.pos.x = (col + 1) as f32 * size.x;
+                    contacts.push(TileContact {
+                        normal: vec2(1.0, 0.0),
+                        tile: (col, row),
+                    });
+                }
+            } else {
+                aabb = lifted;
+                contacts.push(TileContact {
+                    normal: vec2(0.0, -1.0),
+                    tile: (col, row),
+                });
+            }
+        }
+    }
+
+    // Up or down
+    if delta.y != 0.0 {
+        let (cols_lo, cols_hi) = span(aabb.left(), aabb.right(), size.x);
+        let target = if delta.y > 0.0 {
+            aabb.bottom() + delta.y
+        } else {
+            aabb.top() + delta.y
+        };
+        // Start from the row the leading edge is in: going down there might be a slope in it
+        // below the feet, and going up the head might already be poking into a ceiling
+        let rows: Box<dyn Iterator<Item = i32>> = if delta.y > 0.0 {
+            Box::new(floor_div(aabb.bottom(), size.y)..=ceil_div(target, size.y) - 1)
+        } else {
+            Box::new((floor_div(target, size.y)..=ceil_div(aabb.top(), size.y) - 1).rev())
+        };
+        let foot_x = aabb.center().x;
+
+        // Where the leading edge stops, and what it stopped on
+        let mut blocked: Option<(f32, TileContact)> = None;
+        for row in rows {
+            for col in cols_lo..=cols_hi {
+                let shape = tiles.shape_at(col, row);
+                let top = row as f32 * size.y;
+                let stop = if delta.y > 0.0 {
+                    match shape {
+                        TileShape::Solid => Some(top).filter(|&top| top >= aabb.bottom() - EPSILON),
+                        TileShape::OneWay => Some(top).filter(|&top| {
+                            top >= aabb.bottom() - EPSILON && start.bottom() <= top + EPSILON
+                        }),
+                        // Land on the surface, going by the part of it closest to the middle of the feet
+                        TileShape::Slope { .. } => {
+                            let height = shape.slope_height(foot_x / size.x - col as f32).unwrap();
+                            let surface = top + (1.0 - height) * size.y;
+                            Some(surface).filter(|&surface| {
+                                aabb.bottom() <= surface + EPSILON && surface < target
+                            })
+                        }
+                        TileShape::Empty => None,
+                    }
+                } else {
+                    match shape {
+                        TileShape::Solid => Some(top + size.y),
+                        // Slopes are solid underneath, but the box might be standing on this one
+                        TileShape::Slope { .. } => {
+                            Some(top + size.y).filter(|&bottom| bottom <= aabb.top() + EPSILON)
+                        }
+                        TileShape::Empty | TileShape::OneWay => None,
+                    }
+                };
+
+                if let Some(stop) = stop {
+                    let normal = if delta.y > 0.0 {
+                        slope_normal(shape, size)
+                    } else {
+                        vec2(0.0, 1.0)
+                    };
+                    let closer = match blocked {
+                        Some((best, _)) if delta.y > 0.0 => stop < best,
+                        Some((best, _)) => stop > best,
+                        None => true,
+                    };
+                    if closer {
+                        blocked = Some((
+                            stop,
+                            TileContact {
+                                normal,
+                                tile: (col, row),
+                            },
+                        ));
+                    }
+                }
+            }
+            // Everything in later rows is further away
+            if blocked.is_some() {
+                break;
+            }
+        }
+
+        match blocked {
+            Some((stop, contact)) => {
+                if delta.y > 0.0 {
+                    aabb.pos.y = stop - aabb.size.y;
+                } else {
+                    aabb.pos.y = stop;
+                }
+                contacts.push(contact);
+            }
+            None => aabb.pos.y += delta.y,
+        }
+    }
+
+    // Settle onto slopes, going by the middle of the box's feet
+    if delta.y >= 0.0 {
+        let foot_x = aabb.center().x;
+        let col = floor_div(foot_x, size.x);
+        let feet_row = floor_div(aabb.bottom() - EPSILON, size.y);
+        // Walking down a slope, stick to it instead of stepping off into the air,
+        // unless something's already holding the box up
+        let supported = contacts.iter().any(|contact| contact.normal.y < 0.0);
+        let stick = if supported {
+            0.0
+        } else {
+            delta.x.abs() + EPSILON
+        };
+
+        for row in feet_row..=feet_row + 1 {
+            let shape = tiles.shape_at(col, row);
+            let height = match shape.slope_height(foot_x / size.x - col as f32) {
+                Some(height) => height,
+                None => continue,
+            };
+            let surface = (row as f32 + 1.0 - height) * size.y;
+            let lift = aabb.bottom() - surface;
+            if lift > -stick {
+                aabb.pos.y = surface - aabb.size.y;
+                contacts.push(TileContact {
+                    normal: slope_normal(shape, size),
+                    tile: (col, row),
+                });
+                break;
+            }
+        }
+    }
+
+    Movement {
+        aabb,
+        moved: aabb.pos - start.pos,
+        contacts,
+    }
+}
+
+/// The normal of a slope's surface.
+fn slope_normal(shape: TileShape, size: Vec2) -> Vec2 {
+    match shape {
+        TileShape::Slope { left, right } => {
+            // The surface goes from the left edge to the right edge, and up is negative Y
+            let along = vec2(size.x, -(right - left) * size.y);
+            vec2(along.y, -along.x).normalize()
+        }
+        _ => vec2(0.0, -1.0),
+    }
+}
+
+/// The first and last tile a range from `lo` to `hi` is in.
+/// A range ending exactly on a tile's edge isn't in that tile.
+fn span(lo: f32, hi: f32, tile: f32) -> (i32, i32) {
+    (floor_div(lo, tile), ceil_div(hi, tile) - 1)
+}
+
+fn floor_div(pos: f32, tile: f32) -> i32 {
+    (pos / tile).floor() as i32
+}
+
+fn ceil_div(pos: f32, tile: f32) -> i32 {
+    (pos / tile).ceil() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 16.0;
+
+    /// An empty grid with a solid floor along the bottom row.
+    fn grid(tiles: &[((i32, i32), TileShape)]) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(8, 8, vec2(TILE, TILE));
+        for x in 0..8 {
+            grid.set(x, 7, TileShape::Solid);
+        }
+        for &((x, y), shape) in tiles {
+            grid.set(x, y, shape);
+        }
+        grid
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn lands_on_slopes() {
+        for (shape, normal_x) in [(TileShape::SLOPE_UP, -1.0), (TileShape::SLOPE_DOWN, 1.0)] {
+            let tiles = grid(&[((2, 4), shape)]);
+            // Halfway across the slope, so its surface is halfway up the tile
+            let surface = 4.5 * TILE;
+
+            // Falling more than a tile in one tick still lands on it
+            let moved = move_aabb(&tiles, Aabb::new(36.0, 0.0, 8.0, 8.0), vec2(0.0, 80.0));
+            assert!(close(moved.aabb.bottom(), surface), "{:?}", moved);
+            assert!(moved.on_ground());
+            let normal = moved.contacts.last().unwrap().normal;
+            assert_eq!(normal.x.signum(), normal_x);
+
+            // Jumping off it doesn't bump into it
+            let standing = Aabb::new(36.0, surface - 4.0, 8.0, 4.0);
+            let moved = move_aabb(&tiles, standing, vec2(0.0, -10.0));
+            assert!(close(moved.aabb.bottom(), surface - 10.0), "{:?}", moved);
+            assert!(!moved.on_ceiling());
+        }
+    }
+
+    #[test]
+    fn one_way_platforms() {
+        let tiles = grid(&[((2, 3), TileShape::OneWay)]);
+        let top = 3.0 * TILE;
+
+        // Jump up through it
+        let below = Aabb::new(36.0, top + 12.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, below, vec2(0.0, -24.0));
+        assert!(close(moved.aabb.top(), top - 12.0), "{:?}", moved);
+        assert!(moved.contacts.is_empty());
+
+        // Fall back down onto it
+        let moved = move_aabb(&tiles, moved.aabb, vec2(0.0, 24.0));
+        assert!(close(moved.aabb.bottom(), top), "{:?}", moved);
+        assert!(moved.on_ground());
+
+        // Something that started partway through it falls on through
+        let inside = Aabb::new(36.0, top - 4.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, inside, vec2(0.0, 8.0));
+        assert!(close(moved.aabb.bottom(), top + 12.0), "{:?}", moved);
+    }
+
+    #[test]
+    fn steps_up_over_full_width() {
+        // A slope up to the ground at the top of it
+        let tiles = grid(&[
+            ((1, 6), TileShape::SLOPE_UP),
+            ((2, 6), TileShape::Solid),
+            ((3, 6), TileShape::Solid),
+        ]);
+        let ground = 6.0 * TILE;
+
+        // The middle of the box is still over the slope, a bit below the ground,
+        // when its front walks into the ground
+        let foot = 26.0;
+        let on_slope = 7.0 * TILE - (foot - TILE);
+        let walking = Aabb::new(foot - 4.0, on_slope - 8.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, walking, vec2(4.0, 0.0));
+        assert!(close(moved.aabb.bottom(), ground), "{:?}", moved);
+        assert!(close(moved.aabb.left(), foot), "{:?}", moved);
+        assert!(moved.on_ground());
+        assert_eq!(moved.on_wall(), None);
+
+        // Walking off flat ground onto the low edge of a slope steps up onto it
+        let tiles = grid(&[(
+            (2, 6),
+            TileShape::Slope {
+                left: 0.25,
+                right: 0.75,
+            },
+        )]);
+        let walking = Aabb::new(22.0, 7.0 * TILE - 8.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, walking, vec2(4.0, 0.0));
+        assert!(close(moved.aabb.bottom(), 6.75 * TILE), "{:?}", moved);
+        assert_eq!(moved.on_wall(), None);
+
+        // But walking into a ledge off flat ground is a wall
+        let mut tiles = tiles;
+        tiles.set(2, 6, TileShape::Solid);
+        let ledge = Aabb::new(22.0, 7.0 * TILE - 8.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, ledge, vec2(4.0, 0.0));
+        assert!(close(moved.aabb.right(), 2.0 * TILE), "{:?}", moved);
+        assert_eq!(moved.on_wall(), Some(vec2(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn hits_ceilings() {
+        let tiles = grid(&[((2, 2), TileShape::Solid)]);
+        let ceiling = 3.0 * TILE;
+
+        // Fast enough to go right past it
+        let below = Aabb::new(36.0, ceiling + 20.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, below, vec2(0.0, -60.0));
+        assert!(close(moved.aabb.top(), ceiling), "{:?}", moved);
+        assert!(moved.on_ceiling());
+
+        // Already poking into it
+        let inside = Aabb::new(36.0, ceiling - 4.0, 8.0, 8.0);
+        let moved = move_aabb(&tiles, inside, vec2(0.0, -4.0));
+        assert!(close(moved.aabb.top(), ceiling), "{:?}", moved);
+        assert!(moved.on_ceiling());
+    }
+}
@@ -8,25 +8,51 @@ pub fn hexcolor(code: u32) -> Color {
     Color::from_rgba(r, g, b, a)
 }
 
+/// Get the mouse position in canvas pixels straight from macroquad.
+///
+/// Inside `Gamemode::update` prefer `InputSubscriber::mouse_pos`, which is part of the
+/// same snapshot as the rest of the input.
 pub fn mouse_position_pixel() -> (f32, f32) {
     let (mx, my) = mouse_position();
-    let (wd, hd) = width_height_deficit();
-    let mx = (mx - wd / 2.0) / ((screen_width() - wd) / WIDTH);
-    let my = (my - hd / 2.0) / ((screen_height() - hd) / HEIGHT);
-    (mx, my)
+    window_to_pixel(mx, my, screen_width(), screen_height())
+}
+
+/// Convert a position on a window of the given size to a position on the canvas.
+pub fn window_to_pixel(x: f32, y: f32, screen_width: f32, screen_height: f32) -> (f32, f32) {
+    let (wd, hd) = width_height_deficit_of(screen_width, screen_height);
+    let x = (x - wd / 2.0) / ((screen_width - wd) / WIDTH);
+    let y = (y - hd / 2.0) / ((screen_height - hd) / HEIGHT);
+    (x, y)
 }
 
 pub fn width_height_deficit() -> (f32, f32) {
-    if (screen_width() / screen_height()) > ASPECT_RATIO {
+    width_height_deficit_of(screen_width(), screen_height())
+}
+
+/// How much wider/taller a window of the given size is than the content.
+pub fn width_height_deficit_of(screen_width: f32, screen_height: f32) -> (f32, f32) {
+    if (screen_width / screen_height) > ASPECT_RATIO {
         // it's too wide! put bars on the sides!
         // the height becomes the authority on how wide to draw
-        let expected_width = screen_height() * ASPECT_RATIO;
-        (screen_width() - expected_width, 0.0f32)
+        let expected_width = screen_height * ASPECT_RATIO;
+        (screen_width - expected_width, 0.0f32)
     } else {
         // it's too tall! put bars on the ends!
         // the width is the authority
-        let expected_height = screen_width() / ASPECT_RATIO;
-        (0.0f32, screen_height() - expected_height)
+        let expected_height = screen_width / ASPECT_RATIO;
+        (0.0f32, screen_height - expected_height)
+    }
+}
+
+/// Camera for drawing onto a canvas-sized render target in canvas pixels.
+pub fn canvas_camera(target: RenderTarget) -> Camera2D {
+    // These divides and multiplies are required to get the camera in the center of the screen
+    // and having it fill everything.
+    Camera2D {
+        render_target: Some(target),
+        zoom: vec2(WIDTH.recip() * 2.0, HEIGHT.recip() * 2.0),
+        target: vec2(WIDTH / 2.0, HEIGHT / 2.0),
+        ..Default::default()
     }
 }
 
@@ -0,0 +1,220 @@
+use cogs_gamedev::grids::ICoord;
+
+use super::{index, Passability};
+
+/// Which tiles can be seen from somewhere.
+#[derive(Debug, Clone)]
+pub struct FieldOfView {
+    width: usize,
+    height: usize,
+    visible: Vec<bool>,
+}
+
+impl FieldOfView {
+    pub fn is_visible(&self, pos: ICoord) -> bool {
+        pos.x >= 0
+            && pos.y >= 0
+            && (pos.x as usize) < self.width
+            && (pos.y as usize) < self.height
+            && self.visible[pos.y as usize * self.width + pos.x as usize]
+    }
+
+    /// Every visible tile, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = ICoord> + '_ {
+        let width = self.width;
+        self.visible
+            .iter()
+            .enumerate()
+            .filter(|(_, visible)| **visible)
+            .map(move |(idx, _)| ICoord::new((idx % width) as isize, (idx / width) as isize))
+    }
+}
+
+/// Which tiles can be seen from `origin` within `radius` tiles, with symmetric shadowcasting.
+///
+/// Opaque tiles are visible themselves but hide what's behind them.
+/// The origin is always visible.
+/// Sight goes both ways: if one open tile can see another, the other can see it back.
+pub fn field_of_view<G: Passability + ?Sized>(
+    grid: &G,
+    origin: ICoord,
+    radius: u32,
+) -> FieldOfView {
+    let (width, height) = grid.size();
+    let mut fov = FieldOfView {
+        width,
+        height,
+        visible: vec![false; width * height],
+    };
+    if let Some(idx) = index(grid, origin) {
+        fov.visible[idx] = true;
+    }
+
+    let mut caster = Caster {
+        grid,
+        fov: &mut fov,
+        origin,
+        radius: radius as isize,
+        quadrant: (0, 0, 0, 0),
+    };
+    // How to turn (depth, column) in the north quadrant into each of the others
+    const QUADRANTS: [(isize, isize, isize, isize); 4] =
+        [(0, 1, -1, 0), (0, 1, 1, 0), (1, 0, 0, 1), (-1, 0, 0, 1)];
+    for &quadrant in QUADRANTS.iter() {
+        caster.quadrant = quadrant;
+        caster.scan(1, Slope::new(-1, 1), Slope::new(1, 1));
+    }
+    fov
+}
+
+/// A slope from the origin, in columns per row, kept as a fraction so ties come out exact.
+#[derive(Debug, Clone, Copy)]
+struct Slope {
+    num: isize,
+    /// Always positive
+    den: isize,
+}
+
+impl Slope {
+    fn new(num: isize, den: isize) -> Self {
+        Self { num, den }
+    }
+
+    /// The slope to the near edge of the tile at `col` in the row `depth` away.
+    fn to_edge(depth: isize, col: isize) -> Self {
+        Self::new(2 * col - 1, 2 * depth)
+    }
+
+    /// The column the slope is in at `depth`, rounding a slope right on an edge up.
+    fn col_rounding_up(self, depth: isize) -> isize {
+        (2 * depth * self.num + self.den).div_euclid(2 * self.den)
+    }
+
+    /// The column the slope is in at `depth`, rounding a slope right on an edge down.
+    fn col_rounding_down(self, depth: isize) -> isize {
+        -(-2 * depth * self.num + self.den).div_euclid(2 * self.den)
+    }
+
+    /// Is the center of the tile at `col` in the row `depth` away on this side of the slope?
+    fn below(self, depth: isize, col: isize) -> bool {
+        col * self.den <= depth * self.num
+    }
+
+    fn above(self, depth: isize, col: isize) -> bool {
+        col * self.den >= depth * self.num
+    }
+}
+
+/// Scans the quadrants one at a time, row by row out from the origin.
+struct Caster<'a, G: ?Sized> {
+    grid: &'a G,
+    fov: &'a mut FieldOfView,
+    origin: ICoord,
+    radius: isize,
+    /// Turns (depth, column) into an offset from the origin
+    quadrant: (isize, isize, isize, isize),
+}
+
+impl<G: Passability + ?Sized> Caster<'_, G> {
+    fn pos(&self, depth: isize, col: isize) -> ICoord {
+        let (dx, cx, dy, cy) = self.quadrant;
+        ICoord::new(
+            self.origin.x + depth * dx + col * cx,
+            self.origin.y + depth * dy + col * cy,
+        )
+    }
+
+    fn opaque(&self, pos: ICoord) -> bool {
+        !self.grid.in_bounds(pos) || self.grid.opaque(pos)
+    }
+
+    /// Light up the row `depth` away between the two slopes, and everything past it that
+    /// isn't in the shadow of a wall.
+    fn scan(&mut self, depth: isize, mut start: Slope, end: Slope) {
+        if depth > self.radius {
+            return;
+        }
+        let mut prev_opaque = None;
+        for col in start.col_rounding_up(depth)..=end.col_rounding_down(depth) {
+            let pos = self.pos(depth, col);
+            let opaque = self.opaque(pos);
+            // Open tiles are only lit if their center is in the light, so sight is symmetric
+            let lit = opaque || (start.above(depth, col) && end.below(depth, col));
+            if lit && depth * depth + col * col <= self.radius * self.radius {
+                if let Some(idx) = index(self.grid, pos) {
+                    self.fov.visible[idx] = true;
+                }
+            }
+
+            match (prev_opaque, opaque) {
+                (Some(true), false) => start = Slope::to_edge(depth, col),
+                (Some(false), true) => self.scan(depth + 1, start, Slope::to_edge(depth, col)),
+                _ => {}
+            }
+            prev_opaque = Some(opaque);
+        }
+        if prev_opaque == Some(false) {
+            self.scan(depth + 1, start, end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::grid::parse_grid;
+
+    #[test]
+    fn sight_goes_both_ways() {
+        let grid = parse_grid(&[
+            "..#.....", "........", "...#..#.", ".#......", "....##..", "#.......",
+        ]);
+        let (w, h) = grid.size();
+        let tiles: Vec<ICoord> = (0..h as isize)
+            .flat_map(|y| (0..w as isize).map(move |x| ICoord::new(x, y)))
+            .filter(|&pos| grid.passable(pos))
+            .collect();
+        for &a in tiles.iter() {
+            let from_a = field_of_view(&grid, a, 20);
+            for &b in tiles.iter() {
+                let from_b = field_of_view(&grid, b, 20);
+                assert_eq!(
+                    from_a.is_visible(b),
+                    from_b.is_visible(a),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn walls_hide_whats_around_the_corner() {
+        let grid = parse_grid(&[
+            "#####", //
+            "#...#", "###.#", "###.#",
+        ]);
+        let fov = field_of_view(&grid, ICoord::new(1, 1), 20);
+        assert!(fov.is_visible(ICoord::new(3, 1)));
+        // The wall on the corner is visible, but not the corridor behind it
+        assert!(fov.is_visible(ICoord::new(2, 2)));
+        assert!(!fov.is_visible(ICoord::new(3, 3)));
+        assert!(!fov.is_visible(ICoord::new(2, 3)));
+    }
+
+    #[test]
+    fn sees_up_to_the_edge() {
+        let grid = [[true; 4]; 3];
+        let fov = field_of_view(&grid, ICoord::new(0, 0), 20);
+        assert_eq!(fov.iter().count(), 12);
+        assert!(fov.is_visible(ICoord::new(3, 2)));
+        assert!(!fov.is_visible(ICoord::new(-1, 0)));
+        assert!(!fov.is_visible(ICoord::new(4, 0)));
+
+        let fov = field_of_view(&grid, ICoord::new(0, 0), 2);
+        assert!(fov.is_visible(ICoord::new(2, 0)));
+        assert!(fov.is_visible(ICoord::new(1, 1)));
+        assert!(!fov.is_visible(ICoord::new(2, 2)));
+    }
+}
@@ -0,0 +1,155 @@
+//! Algorithms over square grids: pathfinding, Dijkstra maps, flood fill and field of view.
+//!
+//! They work over anything that implements `Passability`, which includes
+//! plain 2D arrays of `bool`s, tile layers, and collision grids.
+//!
+//! Everything here is deterministic: neighbors are always visited in the same order
+//! and ties are always broken the same way, so replays find the same paths.
+
+mod fov;
+mod path;
+
+pub use fov::{field_of_view, FieldOfView};
+pub use path::{astar, flood_fill, DijkstraMap};
+
+use cogs_gamedev::grids::{Direction4, Direction8, ICoord};
+
+use crate::utils::{
+    collision::tiles::{CollisionGrid, TileCollider, TileShape},
+    tilemap::TileLayer,
+};
+
+/// A grid that can be walked around and seen through.
+pub trait Passability {
+    /// Width and height in tiles. Everything outside is impassable and opaque.
+    fn size(&self) -> (usize, usize);
+
+    /// Can this tile be walked on? Only called for tiles inside the grid.
+    fn passable(&self, pos: ICoord) -> bool;
+
+    /// How much it costs to step onto this tile. Only called for passable tiles.
+    /// Costs under 1 are treated as 1.
+    fn cost(&self, _pos: ICoord) -> u32 {
+        1
+    }
+
+    /// Does this tile block sight? By default, impassable tiles do.
+    fn opaque(&self, pos: ICoord) -> bool {
+        !self.passable(pos)
+    }
+
+    fn in_bounds(&self, pos: ICoord) -> bool {
+        let (w, h) = self.size();
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < w && (pos.y as usize) < h
+    }
+}
+
+/// Which tiles count as next to each other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Adjacency {
+    /// Just up, down, left and right
+    Four,
+    /// Diagonals too, for the same cost as straight steps.
+    /// Diagonal steps can't cut the corner of an impassable tile.
+    Eight,
+}
+
+impl Adjacency {
+    /// The passable tiles next to `pos`, always in the same order.
+    fn neighbors<G: Passability + ?Sized>(self, grid: &G, pos: ICoord) -> Vec<ICoord> {
+        let open = |pos: ICoord| grid.in_bounds(pos) && grid.passable(pos);
+        match self {
+            Adjacency::Four => Direction4::DIRECTIONS
+                .iter()
+                .map(|dir| pos + *dir)
+                .filter(|&next| open(next))
+                .collect(),
+            Adjacency::Eight => Direction8::DIRECTIONS
+                .iter()
+                .map(|dir| (pos + *dir, dir.deltas()))
+                .filter(|&(next, delta)| {
+                    open(next)
+                        && (delta.x == 0
+                            || delta.y == 0
+                            || (open(ICoord::new(pos.x + delta.x, pos.y))
+                                && open(ICoord::new(pos.x, pos.y + delta.y))))
+                })
+                .map(|(next, _)| next)
+                .collect(),
+        }
+    }
+
+    /// The fewest steps between two tiles, ignoring walls.
+    fn distance(self, a: ICoord, b: ICoord) -> u32 {
+        let dx = (a.x - b.x).unsigned_abs() as u32;
+        let dy = (a.y - b.y).unsigned_abs() as u32;
+        match self {
+            Adjacency::Four => dx + dy,
+            Adjacency::Eight => dx.max(dy),
+        }
+    }
+}
+
+/// Where a tile's info goes in a row-by-row array.
+fn index<G: Passability + ?Sized>(grid: &G, pos: ICoord) -> Option<usize> {
+    if grid.in_bounds(pos) {
+        Some(pos.y as usize * grid.size().0 + pos.x as usize)
+    } else {
+        None
+    }
+}
+
+/// Rows of tiles, `true` where it's passable.
+impl Passability for Vec<Vec<bool>> {
+    fn size(&self) -> (usize, usize) {
+        (self.first().map_or(0, Vec::len), self.len())
+    }
+
+    fn passable(&self, pos: ICoord) -> bool {
+        self[pos.y as usize]
+            .get(pos.x as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+/// Rows of tiles, `true` where it's passable.
+impl<const W: usize, const H: usize> Passability for [[bool; W]; H] {
+    fn size(&self) -> (usize, usize) {
+        (W, H)
+    }
+
+    fn passable(&self, pos: ICoord) -> bool {
+        self[pos.y as usize][pos.x as usize]
+    }
+}
+
+/// A layer of walls: empty tiles are passable and any tile is a wall.
+impl Passability for TileLayer {
+    fn size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    fn passable(&self, pos: ICoord) -> bool {
+        self.get(pos.x as i32, pos.y as i32) == Some(0)
+    }
+}
+
+/// Solid tiles are walls. Everything else, including slopes and platforms, is passable.
+impl Passability for CollisionGrid {
+    fn size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    fn passable(&self, pos: ICoord) -> bool {
+        self.shape_at(pos.x as i32, pos.y as i32) != TileShape::Solid
+    }
+}
+
+/// Rows of tiles, with `#` for walls.
+#[cfg(test)]
+fn parse_grid(rows: &[&str]) -> Vec<Vec<bool>> {
+    rows.iter()
+        .map(|row| row.chars().map(|c| c != '#').collect())
+        .collect()
+}
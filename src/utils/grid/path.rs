@@ -0,0 +1,338 @@
+use cogs_gamedev::grids::ICoord;
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use super::{index, Adjacency, Passability};
+
+/// The cheapest path from `start` to `goal`, including both ends.
+///
+/// `None` if there's no way there, or either end is impassable.
+pub fn astar<G: Passability + ?Sized>(
+    grid: &G,
+    start: ICoord,
+    goal: ICoord,
+    adjacency: Adjacency,
+) -> Option<Vec<ICoord>> {
+    let start_idx = index(grid, start)?;
+    let goal_idx = index(grid, goal)?;
+    if !grid.passable(start) || !grid.passable(goal) {
+        return None;
+    }
+
+    let (w, h) = grid.size();
+    let mut cost_so_far = vec![u32::MAX; w * h];
+    let mut came_from = vec![usize::MAX; w * h];
+    // Entries are (estimated total, insertion order, position).
+    // The insertion order breaks ties so the same grid always gives the same path.
+    let mut open = BinaryHeap::new();
+    let mut counter = 0u64;
+
+    cost_so_far[start_idx] = 0;
+    open.push(Reverse((
+        adjacency.distance(start, goal),
+        counter,
+        start.x,
+        start.y,
+    )));
+
+    while let Some(Reverse((_, _, x, y))) = open.pop() {
+        let pos = ICoord::new(x, y);
+        let idx = index(grid, pos).unwrap();
+        if idx == goal_idx {
+            let mut path = vec![goal];
+            let mut at = goal_idx;
+            while at != start_idx {
+                at = came_from[at];
+                path.push(ICoord::new((at % w) as isize, (at / w) as isize));
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in adjacency.neighbors(grid, pos) {
+            let next_idx = index(grid, next).unwrap();
+            let cost = cost_so_far[idx] + grid.cost(next).max(1);
+            if cost < cost_so_far[next_idx] {
+                cost_so_far[next_idx] = cost;
+                came_from[next_idx] = idx;
+                counter += 1;
+                open.push(Reverse((
+                    cost + adjacency.distance(next, goal),
+                    counter,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+    }
+    None
+}
+
+/// Every passable tile reachable from `start`, nearest first.
+/// Empty if `start` is impassable.
+pub fn flood_fill<G: Passability + ?Sized>(
+    grid: &G,
+    start: ICoord,
+    adjacency: Adjacency,
+) -> Vec<ICoord> {
+    let start_idx = match index(grid, start) {
+        Some(idx) if grid.passable(start) => idx,
+        _ => return Vec::new(),
+    };
+
+    let (w, h) = grid.size();
+    let mut seen = vec![false; w * h];
+    let mut queue = VecDeque::new();
+    let mut out = Vec::new();
+    seen[start_idx] = true;
+    queue.push_back(start);
+    while let Some(pos) = queue.pop_front() {
+        out.push(pos);
+        for next in adjacency.neighbors(grid, pos) {
+            let next_idx = index(grid, next).unwrap();
+            if !seen[next_idx] {
+                seen[next_idx] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    out
+}
+
+/// How far every tile is from the nearest goal.
+///
+/// Monsters can roll downhill on it to chase the player, or uphill to run away,
+/// and any number of them can share one map.
+#[derive(Debug, Clone)]
+pub struct DijkstraMap {
+    width: usize,
+    height: usize,
+    adjacency: Adjacency,
+    /// `None` for tiles that can't reach a goal
+    distances: Vec<Option<u32>>,
+}
+
+impl DijkstraMap {
+    /// Work out the distances to the nearest of the goals. Impassable goals are skipped.
+    pub fn new<G: Passability + ?Sized>(grid: &G, goals: &[ICoord], adjacency: Adjacency) -> Self {
+        let (width, height) = grid.size();
+        let mut distances = vec![None; width * height];
+        let mut open = BinaryHeap::new();
+        let mut counter = 0u64;
+
+        for &goal in goals {
+            if let Some(idx) = index(grid, goal) {
+                if grid.passable(goal) && distances[idx].is_none() {
+                    distances[idx] = Some(0);
+                    counter += 1;
+                    open.push(Reverse((0, counter, goal.x, goal.y)));
+                }
+            }
+        }
+
+        while let Some(Reverse((dist, _, x, y))) = open.pop() {
+            let pos = ICoord::new(x, y);
+            let idx = index(grid, pos).unwrap();
+            if distances[idx] != Some(dist) {
+                // Already found a shorter way here
+                continue;
+            }
+            for next in adjacency.neighbors(grid, pos) {
+                let next_idx = index(grid, next).unwrap();
+                // Going backwards from the goal, so it's the cost of stepping onto this tile
+                let cost = dist + grid.cost(pos).max(1);
+                let shorter = match distances[next_idx] {
+                    Some(old) => cost < old,
+                    None => true,
+                };
+                if shorter {
+                    distances[next_idx] = Some(cost);
+                    counter += 1;
+                    open.push(Reverse((cost, counter, next.x, next.y)));
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            adjacency,
+            distances,
+        }
+    }
+
+    /// How far this tile is from the nearest goal, if it can get to one at all.
+    pub fn get(&self, pos: ICoord) -> Option<u32> {
+        if pos.x >= 0
+            && pos.y >= 0
+            && (pos.x as usize) < self.width
+            && (pos.y as usize) < self.height
+        {
+            self.distances[pos.y as usize * self.width + pos.x as usize]
+        } else {
+            None
+        }
+    }
+
+    /// The neighbor that's closest to a goal, if any is closer than `pos`.
+    pub fn downhill(&self, pos: ICoord) -> Option<ICoord> {
+        self.best_neighbor(pos, |here, there| there < here)
+    }
+
+    /// The neighbor that's furthest from every goal, if any is further than `pos`.
+    pub fn uphill(&self, pos: ICoord) -> Option<ICoord> {
+        self.best_neighbor(pos, |here, there| there > here)
+    }
+
+    /// The steps from `pos` to the nearest goal, not including `pos`.
+    /// Empty if it's already on a goal or can't get to one.
+    pub fn path_from(&self, pos: ICoord) -> Vec<ICoord> {
+        let mut path = Vec::new();
+        let mut at = pos;
+        while let Some(next) = self.downhill(at) {
+            path.push(next);
+            at = next;
+        }
+        path
+    }
+
+    /// The first neighbor whose distance beats all the others by `better`.
+    fn best_neighbor(&self, pos: ICoord, better: impl Fn(u32, u32) -> bool) -> Option<ICoord> {
+        let mut best_dist = self.get(pos)?;
+        let mut best = None;
+        // Neighbors that could reach a goal are all passable, so walls don't matter here,
+        // but corners still can't be cut.
+        let steps: &[(isize, isize)] = match self.adjacency {
+            Adjacency::Four => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Adjacency::Eight => &[
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+            ],
+        };
+        for &(dx, dy) in steps {
+            let next = ICoord::new(pos.x + dx, pos.y + dy);
+            let corner_open = dx == 0
+                || dy == 0
+                || (self.get(ICoord::new(pos.x + dx, pos.y)).is_some()
+                    && self.get(ICoord::new(pos.x, pos.y + dy)).is_some());
+            if let Some(dist) = self.get(next) {
+                if corner_open && better(best_dist, dist) {
+                    best_dist = dist;
+                    best = Some(next);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::grid::parse_grid;
+
+    fn assert_walkable<G: Passability>(grid: &G, path: &[ICoord]) {
+        for step in path.windows(2) {
+            let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+            assert_eq!(dx.abs() + dy.abs(), 1, "{:?}", step);
+        }
+        assert!(path.iter().all(|&pos| grid.passable(pos)));
+    }
+
+    #[test]
+    fn astar_finds_the_shortest_path() {
+        let grid = parse_grid(&[
+            "......", //
+            ".####.", ".#..#.", ".#..#.", "......",
+        ]);
+        let (start, goal) = (ICoord::new(0, 0), ICoord::new(2, 2));
+        let path = astar(&grid, start, goal, Adjacency::Four).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        // Down the left side and in through the bottom, not round the right
+        assert_eq!(path.len(), 9);
+        assert_walkable(&grid, &path);
+
+        assert_eq!(
+            astar(&grid, start, ICoord::new(1, 1), Adjacency::Four),
+            None
+        );
+    }
+
+    #[test]
+    fn astar_breaks_ties_the_same_way() {
+        let grid = parse_grid(&[
+            ".....", //
+            ".....", "..#..", ".....", ".....",
+        ]);
+        let (start, goal) = (ICoord::new(0, 0), ICoord::new(4, 4));
+        let path = astar(&grid, start, goal, Adjacency::Four).unwrap();
+        assert_eq!(path.len(), 9);
+        assert_walkable(&grid, &path);
+        for _ in 0..10 {
+            assert_eq!(astar(&grid, start, goal, Adjacency::Four).unwrap(), path);
+        }
+    }
+
+    #[test]
+    fn dijkstra_distances() {
+        let grid = parse_grid(&[
+            "...#.", //
+            ".#.#.", "...#.",
+        ]);
+        let map = DijkstraMap::new(&grid, &[ICoord::new(0, 0)], Adjacency::Four);
+        let expected = [
+            [Some(0), Some(1), Some(2), None, None],
+            [Some(1), None, Some(3), None, None],
+            [Some(2), Some(3), Some(4), None, None],
+        ];
+        for (y, row) in expected.iter().enumerate() {
+            for (x, &distance) in row.iter().enumerate() {
+                let pos = ICoord::new(x as isize, y as isize);
+                assert_eq!(map.get(pos), distance, "{:?}", pos);
+            }
+        }
+        assert_eq!(map.get(ICoord::new(-1, 0)), None);
+        assert!(map.path_from(ICoord::new(4, 0)).is_empty());
+        assert_eq!(map.path_from(ICoord::new(2, 2)).len(), 4);
+    }
+
+    #[test]
+    fn flood_fill_stays_inside_the_walls() {
+        let grid = parse_grid(&[
+            "..#..", //
+            "..#..", "###..",
+        ]);
+        let start = ICoord::new(0, 0);
+        let filled = flood_fill(&grid, start, Adjacency::Four);
+        assert_eq!(filled.first(), Some(&start));
+        let mut sorted = filled.clone();
+        sorted.sort_by_key(|pos| (pos.y, pos.x));
+        assert_eq!(
+            sorted,
+            vec![
+                ICoord::new(0, 0),
+                ICoord::new(1, 0),
+                ICoord::new(0, 1),
+                ICoord::new(1, 1)
+            ]
+        );
+
+        // Diagonals can't squeeze between two walls touching at the corners
+        let grid = parse_grid(&[
+            ".#..", //
+            "#...",
+        ]);
+        assert_eq!(flood_fill(&grid, start, Adjacency::Eight), vec![start]);
+        assert!(flood_fill(&grid, ICoord::new(1, 0), Adjacency::Four).is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+//! Unlimited undo, redo and restart for turn-based games.
+//!
+//! A `History` owns the game state and remembers how to get back to every earlier version of it.
+//! Each move is stored one of two ways:
+//!
+//! - As a snapshot, with `History::change`. The state before the move is compressed with
+//!   `serdeflate::binzip`, so this works for any state with no extra code.
+//! - As a `Command`, with `History::apply`. Commands know how to undo themselves,
+//!   so they're much smaller than a snapshot when the state is big and moves are small.
+//!
+//! The two can be mixed freely. Making a move after undoing throws away the moves that
+//! could have been redone, and the oldest moves are forgotten once the history gets
+//! bigger than its `HistoryLimits`.
+//!
+//! ```ignore
+//! if let Some(action) = self.history.update(controls)? {
+//!     // play a rewind sound or something
+//! }
+//! if controls.clicked_down(Control::Click) {
+//!     self.history.apply(MovePlayer(dir));
+//! }
+//! ```
+
+use cogs_gamedev::controls::InputHandler;
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::{collections::VecDeque, mem};
+
+use crate::{
+    controls::{Control, InputSubscriber},
+    utils::serdeflate::{binzip, unbinzip},
+};
+
+/// A move that knows how to take itself back.
+pub trait Command<S> {
+    /// Make the move.
+    fn apply(&mut self, state: &mut S);
+
+    /// Take the move back. `state` is always just as `apply` left it.
+    fn undo(&mut self, state: &mut S);
+
+    /// About how many bytes this takes up, for `HistoryLimits::max_bytes`.
+    /// Override this if the command holds onto anything on the heap.
+    fn size(&self) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+/// The command type for histories that only ever store snapshots.
+/// It can't be constructed.
+#[derive(Debug, Clone, Copy)]
+pub enum NoCommand {}
+
+impl<S> Command<S> for NoCommand {
+    fn apply(&mut self, _state: &mut S) {
+        match *self {}
+    }
+
+    fn undo(&mut self, _state: &mut S) {
+        match *self {}
+    }
+}
+
+/// How much history to keep before forgetting the oldest moves.
+///
+/// The most recent move is always kept, even if it's over the limits on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryLimits {
+    /// The most moves to remember, counting both undo and redo
+    pub max_steps: Option<usize>,
+    /// The most bytes of snapshots and commands to remember
+    pub max_bytes: Option<usize>,
+}
+
+impl HistoryLimits {
+    pub const UNLIMITED: HistoryLimits = HistoryLimits {
+        max_steps: None,
+        max_bytes: None,
+    };
+}
+
+impl Default for HistoryLimits {
+    /// Unlimited moves, but no more than 16 MiB of them.
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_bytes: Some(16 * 1024 * 1024),
+        }
+    }
+}
+
+/// What `History::update` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+    Restart,
+}
+
+/// One move in the history.
+#[derive(Debug, Clone)]
+enum Step<C> {
+    /// The compressed state on the other side of the move from the current state:
+    /// before it if it's on the undo side, after it if it's on the redo side.
+    Snapshot(Vec<u8>),
+    Command(C),
+}
+
+/// Game state, plus every move that led up to it.
+///
+/// `S` is the game state and `C` is the command type, if it uses any.
+#[derive(Debug, Clone)]
+pub struct History<S, C = NoCommand> {
+    state: S,
+    /// The state to go back to on restart
+    initial: Vec<u8>,
+    /// Steps before `cursor` can be undone, and steps from it on can be redone.
+    steps: VecDeque<Step<C>>,
+    cursor: usize,
+    /// How big all the steps are together
+    bytes: usize,
+    limits: HistoryLimits,
+}
+
+impl<S, C> History<S, C>
+where
+    S: Serialize + DeserializeOwned,
+    C: Command<S>,
+{
+    /// Start a history from the state the level starts in, with the default limits.
+    pub fn new(state: S) -> anyhow::Result<Self> {
+        Self::with_limits(state, HistoryLimits::default())
+    }
+
+    pub fn with_limits(state: S, limits: HistoryLimits) -> anyhow::Result<Self> {
+        Ok(Self {
+            initial: binzip(&state)?,
+            state,
+            steps: VecDeque::new(),
+            cursor: 0,
+            bytes: 0,
+            limits,
+        })
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Take the state back out, forgetting the history.
+    pub fn into_state(self) -> S {
+        self.state
+    }
+
+    pub fn limits(&self) -> HistoryLimits {
+        self.limits
+    }
+
+    /// Change the limits, forgetting old moves straight away if it's over the new ones.
+    pub fn set_limits(&mut self, limits: HistoryLimits) {
+        self.limits = limits;
+        self.trim();
+    }
+
+    /// Make a move by changing the state directly, snapshotting it first so it can be undone.
+    pub fn change<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> anyhow::Result<R> {
+        let before = binzip(&self.state)?;
+        let out = f(&mut self.state);
+        self.push(Step::Snapshot(before));
+        Ok(out)
+    }
+
+    /// Make a move with a command.
+    pub fn apply(&mut self, mut command: C) {
+        command.apply(&mut self.state);
+        self.push(Step::Command(command));
+    }
+
+    /// Take back the last move. Returns `false` if there wasn't one.
+    pub fn undo(&mut self) -> anyhow::Result<bool> {
+        if self.cursor == 0 {
+            return Ok(false);
+        }
+        self.swap(self.cursor - 1, true)?;
+        self.cursor -= 1;
+        self.trim();
+        Ok(true)
+    }
+
+    /// Make the last undone move again. Returns `false` if there wasn't one.
+    pub fn redo(&mut self) -> anyhow::Result<bool> {
+        if self.cursor == self.steps.len() {
+            return Ok(false);
+        }
+        self.swap(self.cursor, false)?;
+        self.cursor += 1;
+        self.trim();
+        Ok(true)
+    }
+
+    /// Go back to the starting state. Returns `false` if it was already there.
+    ///
+    /// This counts as a move of its own, so a restart by mistake can be undone.
+    pub fn restart(&mut self) -> anyhow::Result<bool> {
+        let before = binzip(&self.state)?;
+        if before == self.initial {
+            return Ok(false);
+        }
+        self.state = unbinzip(&self.initial)?;
+        self.push(Step::Snapshot(before));
+        Ok(true)
+    }
+
+    /// Start over from a new state, like when loading the next level,
+    /// forgetting all the moves so far.
+    pub fn reset(&mut self, state: S) -> anyhow::Result<()> {
+        self.initial = binzip(&state)?;
+        self.state = state;
+        self.steps.clear();
+        self.cursor = 0;
+        self.bytes = 0;
+        Ok(())
+    }
+
+    /// Undo, redo or restart when the player presses `Control::Undo`, `Control::Redo`
+    /// or `Control::Restart`. Call this once per tick.
+    ///
+    /// Holding undo or redo repeats it, to rewind quickly.
+    /// Returns what happened, if anything did.
+    pub fn update(&mut self, controls: &InputSubscriber) -> anyhow::Result<Option<HistoryAction>> {
+        let action = if controls.clicked_down(Control::Restart) {
+            self.restart()?.then_some(HistoryAction::Restart)
+        } else if controls.repeating(Control::Undo) && self.undo()? {
+            Some(HistoryAction::Undo)
+        } else if controls.repeating(Control::Redo) && self.redo()? {
+            Some(HistoryAction::Redo)
+        } else {
+            None
+        };
+        Ok(action)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.cursor < self.steps.len()
+    }
+
+    /// How many moves can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.cursor
+    }
+
+    /// How many moves can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.steps.len() - self.cursor
+    }
+
+    /// About how many bytes the remembered moves take up.
+    pub fn memory_used(&self) -> usize {
+        self.bytes
+    }
+
+    /// Add a move that's already been made, throwing away anything that could be redone.
+    fn push(&mut self, step: Step<C>) {
+        for dropped in self.steps.drain(self.cursor..) {
+            self.bytes -= Self::step_size(&dropped);
+        }
+        self.bytes += Self::step_size(&step);
+        self.steps.push_back(step);
+        self.cursor += 1;
+        self.trim();
+    }
+
+    /// Move the state backwards or forwards across the step at `idx`.
+    fn swap(&mut self, idx: usize, undo: bool) -> anyhow::Result<()> {
+        let step = &mut self.steps[idx];
+        let old_size = Self::step_size(step);
+        match step {
+            Step::Snapshot(other) => {
+                let current = binzip(&self.state)?;
+                self.state = unbinzip(other)?;
+                *other = current;
+            }
+            Step::Command(command) => {
+                if undo {
+                    command.undo(&mut self.state);
+                } else {
+                    command.apply(&mut self.state);
+                }
+            }
+        }
+        self.bytes = self.bytes - old_size + Self::step_size(&self.steps[idx]);
+        Ok(())
+    }
+
+    /// Forget the oldest moves until it's back under the limits.
+    fn trim(&mut self) {
+        while self.steps.len() > 1 && self.cursor > 0 && self.over_limits() {
+            let dropped = self.steps.pop_front().unwrap();
+            self.bytes -= Self::step_size(&dropped);
+            self.cursor -= 1;
+        }
+    }
+
+    fn over_limits(&self) -> bool {
+        let too_many = match self.limits.max_steps {
+            Some(max) => self.steps.len() > max,
+            None => false,
+        };
+        let too_big = match self.limits.max_bytes {
+            Some(max) => self.bytes > max,
+            None => false,
+        };
+        too_many || too_big
+    }
+
+    fn step_size(step: &Step<C>) -> usize {
+        match step {
+            Step::Snapshot(data) => data.len(),
+            Step::Command(command) => command.size(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_only_counts_when_it_changes_something() {
+        let mut history: History<Vec<i32>> = History::new(vec![1, 2, 3]).unwrap();
+        assert!(!history.restart().unwrap());
+        assert_eq!(history.undo_len(), 0);
+
+        history.change(|state| state.push(4)).unwrap();
+        assert!(history.restart().unwrap());
+        assert_eq!(history.state(), &vec![1, 2, 3]);
+        assert!(!history.restart().unwrap());
+        assert_eq!(history.undo_len(), 2);
+
+        history.undo().unwrap();
+        assert_eq!(history.state(), &vec![1, 2, 3, 4]);
+    }
+}
@@ -1,6 +1,17 @@
 #[allow(dead_code)]
 pub mod button;
+pub mod camera;
+pub mod collision;
 pub mod draw;
+pub mod grid;
+pub mod history;
+pub mod particles;
 pub mod profile;
+pub mod save;
+pub mod script;
 pub mod serdeflate;
+pub mod snapshot;
 pub mod text;
+pub mod text_field;
+pub mod tilemap;
+pub mod tween;
@@ -0,0 +1,318 @@
+//! Particles for sparks, dust, smoke and the like.
+//!
+//! A mode owns an `Emitter` per effect, updates it every tick, and hands a clone to its drawer.
+//! Each emitter draws its randomness from its own `GameRng` forked off the engine's,
+//! so effects come out the same on every replay.
+//!
+//! ```ignore
+//! let mut sparks = Emitter::new(
+//!     ParticleConfig {
+//!         lifetime: (0.3, 0.6),
+//!         speed: (40.0, 80.0),
+//!         spread: PI,
+//!         gravity: vec2(0.0, 200.0),
+//!         colors: Gradient::hex(&[(0.0, 0xffffffff), (0.3, 0xffd040ff), (1.0, 0xa0200000)]),
+//!         ..Default::default()
+//!     },
+//!     EmitMode::Manual,
+//!     pos,
+//!     resources.rng(),
+//! );
+//! sparks.burst(20);
+//! ```
+
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use crate::{
+    assets::{Assets, TextureKey},
+    boilerplates::FrameInfo,
+    rng::GameRng,
+    utils::{draw::hexcolor, snapshot::Shared},
+};
+
+/// Colors to fade between over a particle's life.
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    /// How far through the life from 0.0 to 1.0, and the color there. Sorted by the time.
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    /// Make a gradient from `(time, color)` pairs. They don't need to be in order.
+    pub fn new(mut stops: Vec<(f32, Color)>) -> Self {
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { stops }
+    }
+
+    /// Make a gradient from `(time, RRGGBBAA hex code)` pairs.
+    pub fn hex(stops: &[(f32, u32)]) -> Self {
+        Self::new(
+            stops
+                .iter()
+                .map(|&(time, code)| (time, hexcolor(code)))
+                .collect(),
+        )
+    }
+
+    pub fn solid(color: Color) -> Self {
+        Self::new(vec![(0.0, color)])
+    }
+
+    /// The color this far through, from 0.0 to 1.0.
+    pub fn sample(&self, t: f32) -> Color {
+        let after = self.stops.iter().position(|(time, _)| *time > t);
+        match after {
+            None => self.stops.last().map_or(WHITE, |(_, color)| *color),
+            Some(0) => self.stops[0].1,
+            Some(idx) => {
+                let (t0, c0) = self.stops[idx - 1];
+                let (t1, c1) = self.stops[idx];
+                let t = (t - t0) / (t1 - t0);
+                Color::new(
+                    c0.r + (c1.r - c0.r) * t,
+                    c0.g + (c1.g - c0.g) * t,
+                    c0.b + (c1.b - c0.b) * t,
+                    c0.a + (c1.a - c0.a) * t,
+                )
+            }
+        }
+    }
+}
+
+/// Draw particles as frames out of a sprite sheet instead of squares.
+#[derive(Debug, Clone)]
+pub struct SpriteFrames {
+    pub texture: TextureKey,
+    /// Size of one frame in pixels. Frames go left to right along the top of the sheet.
+    pub frame_size: Vec2,
+    pub frames: u32,
+    /// Frames per second, or `None` to play through them exactly once over the particle's life.
+    pub fps: Option<f32>,
+}
+
+/// How particles from an emitter look and move.
+#[derive(Debug, Clone)]
+pub struct ParticleConfig {
+    /// Lowest and highest lifetime in seconds
+    pub lifetime: (f32, f32),
+    /// Lowest and highest starting speed in pixels per second
+    pub speed: (f32, f32),
+    /// Angle particles fly off at in radians. 0 is right, and positive is clockwise
+    /// because Y is down.
+    pub direction: f32,
+    /// How far either side of `direction` particles can go, in radians.
+    /// `PI` goes every which way.
+    pub spread: f32,
+    /// Half the size of the box around the emitter that particles start in
+    pub spawn_area: Vec2,
+    /// Acceleration in pixels per second per second
+    pub gravity: Vec2,
+    /// How much of its speed a particle loses per second
+    pub drag: f32,
+    pub colors: Gradient,
+    /// Side length of the square drawn when there's no sprite
+    pub size: f32,
+    pub sprite: Option<SpriteFrames>,
+    /// Past this many, new particles aren't made
+    pub max_particles: usize,
+}
+
+impl Default for ParticleConfig {
+    fn default() -> Self {
+        Self {
+            lifetime: (1.0, 1.0),
+            speed: (20.0, 20.0),
+            direction: 0.0,
+            spread: 0.0,
+            spawn_area: Vec2::ZERO,
+            gravity: Vec2::ZERO,
+            drag: 0.0,
+            colors: Gradient::solid(WHITE),
+            size: 1.0,
+            sprite: None,
+            max_particles: 256,
+        }
+    }
+}
+
+/// When an emitter makes particles on its own.
+#[derive(Debug, Clone, Copy)]
+pub enum EmitMode {
+    /// Only when `burst` is called
+    Manual,
+    /// A steady stream of this many per second
+    Continuous { per_sec: f32 },
+    /// This many at once, every so many seconds
+    Bursts { count: u32, every: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    pos: Vec2,
+    vel: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Makes particles and keeps track of them.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    /// Where new particles come from
+    pub pos: Vec2,
+    pub mode: EmitMode,
+
+    config: Shared<ParticleConfig>,
+    particles: Vec<Particle>,
+    rng: GameRng,
+    /// Seconds saved up towards the next particle or burst
+    carry: f32,
+}
+
+impl Emitter {
+    /// Make an emitter. It forks its own rng off of `rng`, which should be the engine's.
+    pub fn new(config: ParticleConfig, mode: EmitMode, pos: Vec2, rng: &mut GameRng) -> Self {
+        Self {
+            pos,
+            mode,
+            config: Shared::new(config),
+            particles: Vec::new(),
+            rng: rng.fork(),
+            carry: 0.0,
+        }
+    }
+
+    pub fn config(&self) -> &ParticleConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut ParticleConfig {
+        self.config.make_mut()
+    }
+
+    /// Make this many particles right now.
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            if self.particles.len() >= self.config.max_particles {
+                break;
+            }
+            let particle = self.spawn();
+            self.particles.push(particle);
+        }
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let mut between = |(lo, hi): (f32, f32)| {
+            if hi > lo {
+                rng.gen_range(lo..hi)
+            } else {
+                lo
+            }
+        };
+
+        let offset = vec2(
+            between((-config.spawn_area.x, config.spawn_area.x)),
+            between((-config.spawn_area.y, config.spawn_area.y)),
+        );
+        let angle = config.direction + between((-config.spread, config.spread));
+        let speed = between(config.speed);
+        let lifetime = between(config.lifetime);
+        Particle {
+            pos: self.pos + offset,
+            vel: vec2(angle.cos(), angle.sin()) * speed,
+            age: 0.0,
+            lifetime,
+        }
+    }
+
+    /// Move everything along and make new particles. Call this once per tick.
+    pub fn update(&mut self, frame_info: FrameInfo) {
+        let dt = frame_info.dt;
+
+        match self.mode {
+            EmitMode::Manual => {}
+            EmitMode::Continuous { per_sec } => {
+                self.carry += dt * per_sec;
+                let count = self.carry.floor();
+                self.carry -= count;
+                self.burst(count as u32);
+            }
+            EmitMode::Bursts { count, every } => {
+                self.carry += dt;
+                while every > 0.0 && self.carry >= every {
+                    self.carry -= every;
+                    self.burst(count);
+                }
+            }
+        }
+
+        let gravity = self.config.gravity;
+        let keep = (1.0 - self.config.drag * dt).max(0.0);
+        for particle in self.particles.iter_mut() {
+            particle.vel = (particle.vel + gravity * dt) * keep;
+            particle.pos += particle.vel * dt;
+            particle.age += dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    /// How many particles are alive.
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Get rid of all the particles.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Draw all the particles, snapped to whole pixels.
+    pub fn draw(&self, assets: &Assets) {
+        let config = &self.config;
+        for particle in self.particles.iter() {
+            let life = if particle.lifetime > 0.0 {
+                particle.age / particle.lifetime
+            } else {
+                1.0
+            };
+            let color = config.colors.sample(life);
+
+            match &config.sprite {
+                Some(sprite) => {
+                    let frames = sprite.frames.max(1);
+                    let frame = match sprite.fps {
+                        Some(fps) => (particle.age * fps) as u32 % frames,
+                        None => ((life * frames as f32) as u32).min(frames - 1),
+                    };
+                    let corner = (particle.pos - sprite.frame_size / 2.0).round();
+                    draw_texture_ex(
+                        assets.textures.get(sprite.texture),
+                        corner.x,
+                        corner.y,
+                        color,
+                        DrawTextureParams {
+                            source: Some(Rect::new(
+                                frame as f32 * sprite.frame_size.x,
+                                0.0,
+                                sprite.frame_size.x,
+                                sprite.frame_size.y,
+                            )),
+                            ..Default::default()
+                        },
+                    );
+                }
+                None => {
+                    let corner = (particle.pos - vec2(config.size, config.size) / 2.0).round();
+                    draw_rectangle(corner.x, corner.y, config.size, config.size, color);
+                }
+            }
+        }
+    }
+}
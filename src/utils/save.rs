@@ -0,0 +1,97 @@
+//! Turning game state into something that can go in a save file.
+//!
+//! Lots of state holds handles like `Texture2D` that can't be serialized directly.
+//! Put `#[serde(with = "save::texture_key")]` on fields like that, and they're saved as
+//! their `TextureKey` and resolved back into textures on load.
+//! That needs the assets, so it only works inside `with_assets`
+//! (`ModeStack::save` and `ModeStack::load` take care of that).
+//!
+//! There are helpers for macroquad's `Color` and `Vec2` too, which don't implement serde.
+
+use macroquad::prelude::{Color, Texture2D, Vec2};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{cell::Cell, ptr};
+
+use crate::assets::{Assets, TextureKey};
+
+thread_local! {
+    /// The assets to look textures up in, while inside `with_assets`
+    static ASSETS: Cell<*const Assets> = const { Cell::new(ptr::null()) };
+}
+
+/// Run `f` with `assets` available to the serde helpers in here.
+pub fn with_assets<R>(assets: &Assets, f: impl FnOnce() -> R) -> R {
+    /// Puts the old assets back even if `f` panics.
+    struct Restore(*const Assets);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ASSETS.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _restore = Restore(ASSETS.with(|cell| cell.replace(assets)));
+    f()
+}
+
+/// Do something with the assets from `with_assets`, if we're inside it.
+fn current_assets<R>(f: impl FnOnce(Option<&Assets>) -> R) -> R {
+    // SAFETY: the pointer is only non-null while `with_assets` is borrowing the assets
+    ASSETS.with(|cell| f(unsafe { cell.get().as_ref() }))
+}
+
+/// Save a `Texture2D` as its `TextureKey`.
+pub mod texture_key {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(texture: &Texture2D, serializer: S) -> Result<S::Ok, S::Error> {
+        let key = current_assets(|assets| match assets {
+            Some(assets) => assets
+                .textures
+                .key_of(*texture)
+                .ok_or_else(|| ser::Error::custom("This texture doesn't have a `TextureKey`")),
+            None => Err(ser::Error::custom(
+                "Textures can only be saved inside `save::with_assets`",
+            )),
+        })?;
+        key.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Texture2D, D::Error> {
+        let key = TextureKey::deserialize(deserializer)?;
+        current_assets(|assets| match assets {
+            Some(assets) => Ok(assets.textures.get(key)),
+            None => Err(de::Error::custom(
+                "Textures can only be loaded inside `save::with_assets`",
+            )),
+        })
+    }
+}
+
+/// Save a `Color` as its RGBA components.
+pub mod color {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
+}
+
+/// Save a `Vec2` as its X and Y.
+pub mod vec2 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [v.x, v.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(Vec2::new(x, y))
+    }
+}
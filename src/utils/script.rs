@@ -0,0 +1,265 @@
+//! Scripted sequences, written as async blocks.
+//!
+//! Cutscenes and tutorials are a list of steps, each waiting on something:
+//! "show text, wait for Click, move the camera, wait 0.5s".
+//! Writing that as a state machine in `Gamemode::update` gets messy fast,
+//! so instead write it as an async block and let a `Script` poll it once per tick:
+//!
+//! ```ignore
+//! let script = Script::new(|ctx| async move {
+//!     ctx.send(Cutscene::ShowText("Hello!"));
+//!     ctx.wait_for_press(Control::Click).await;
+//!     ctx.send(Cutscene::MoveCamera(vec2(100.0, 0.0)));
+//!     ctx.wait_secs(0.5).await;
+//! });
+//! ```
+//!
+//! Scripts can't borrow the mode that owns them, so they talk to it by sending commands,
+//! which `Script::update` hands back every tick for the mode to carry out.
+
+use cogs_gamedev::controls::InputHandler;
+use enum_map::EnumMap;
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{
+    boilerplates::FrameInfo,
+    controls::{Control, InputSubscriber},
+};
+
+/// A running script, owned by a mode.
+///
+/// `C` is the type of command the script sends to the mode.
+pub struct Script<C> {
+    /// `None` once the script has finished or been cancelled
+    future: Option<Pin<Box<dyn Future<Output = ()>>>>,
+    state: Rc<RefCell<ScriptState<C>>>,
+}
+
+impl<C: 'static> Script<C> {
+    /// Make a script. It starts running on the first `update`.
+    pub fn new<F, Fut>(make: F) -> Self
+    where
+        F: FnOnce(ScriptContext<C>) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let state = Rc::new(RefCell::new(ScriptState {
+            tick: 0,
+            time: 0.0,
+            dt: 0.0,
+            pressed: EnumMap::default(),
+            commands: Vec::new(),
+        }));
+        let future = make(ScriptContext {
+            state: Rc::clone(&state),
+        });
+        Self {
+            future: Some(Box::pin(future)),
+            state,
+        }
+    }
+
+    /// Run the script until it waits on something, and return the commands it sent.
+    ///
+    /// Call this once per tick from `Gamemode::update`. It does nothing once the script is done.
+    pub fn update(&mut self, controls: &InputSubscriber, frame_info: FrameInfo) -> Vec<C> {
+        self.run(frame_info.dt, |control| controls.clicked_down(control))
+    }
+
+    /// `update`, with the tick's input already boiled down to which controls were pressed.
+    fn run(&mut self, dt: f32, pressed: impl Fn(Control) -> bool) -> Vec<C> {
+        let future = match &mut self.future {
+            Some(it) => it,
+            None => return Vec::new(),
+        };
+
+        {
+            let mut state = self.state.borrow_mut();
+            state.tick += 1;
+            state.time += dt as f64;
+            state.dt = dt;
+            for (control, was_pressed) in state.pressed.iter_mut() {
+                *was_pressed = pressed(control);
+            }
+        }
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        if future.as_mut().poll(&mut cx).is_ready() {
+            self.future = None;
+        }
+
+        std::mem::take(&mut self.state.borrow_mut().commands)
+    }
+
+    /// Stop the script where it is. It never runs again.
+    pub fn cancel(&mut self) {
+        self.future = None;
+        self.state.borrow_mut().commands.clear();
+    }
+
+    /// Has the script finished or been cancelled?
+    pub fn finished(&self) -> bool {
+        self.future.is_none()
+    }
+}
+
+/// What's shared between a `Script` and the code inside it.
+struct ScriptState<C> {
+    /// How many times the script has been updated
+    tick: u64,
+    /// Seconds of game time since the script started
+    time: f64,
+    dt: f32,
+    /// Which controls were pressed this tick
+    pressed: EnumMap<Control, bool>,
+    /// Commands sent this tick
+    commands: Vec<C>,
+}
+
+/// The script's handle on the outside world.
+///
+/// Each of the `wait` functions finishes on a later tick than the one it was called on,
+/// so two waits in a row never both finish off the same tick.
+pub struct ScriptContext<C> {
+    state: Rc<RefCell<ScriptState<C>>>,
+}
+
+impl<C> Clone for ScriptContext<C> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Rc::clone(&self.state),
+        }
+    }
+}
+
+impl<C> ScriptContext<C> {
+    /// Send a command to the mode. It gets it at the end of this tick's `update`.
+    pub fn send(&self, command: C) {
+        self.state.borrow_mut().commands.push(command);
+    }
+
+    /// Seconds of game time since the script started.
+    pub fn time(&self) -> f64 {
+        self.state.borrow().time
+    }
+
+    /// Seconds of game time this tick took.
+    pub fn dt(&self) -> f32 {
+        self.state.borrow().dt
+    }
+
+    /// Wait until the next tick.
+    pub fn next_tick(&self) -> impl Future<Output = ()> {
+        self.wait_ticks(1)
+    }
+
+    /// Wait this many ticks. Zero is treated as one.
+    pub fn wait_ticks(&self, ticks: u64) -> impl Future<Output = ()> {
+        let end = self.state.borrow().tick + ticks.max(1);
+        self.wait_for(move |state| state.borrow().tick >= end)
+    }
+
+    /// Wait this many seconds of game time.
+    pub fn wait_secs(&self, secs: f32) -> impl Future<Output = ()> {
+        let end = self.time() + secs as f64;
+        self.wait_for(move |state| state.borrow().time >= end)
+    }
+
+    /// Wait until the control is pressed.
+    pub fn wait_for_press(&self, control: Control) -> impl Future<Output = ()> {
+        self.wait_for(move |state| state.borrow().pressed[control])
+    }
+
+    /// Wait until the condition is true. It's checked once per tick.
+    ///
+    /// The condition is free to use the context, like sending a command.
+    pub fn wait_until(
+        &self,
+        mut condition: impl FnMut() -> bool + Unpin,
+    ) -> impl Future<Output = ()> {
+        self.wait_for(move |_| condition())
+    }
+
+    fn wait_for<F>(&self, condition: F) -> WaitFor<C, F>
+    where
+        F: FnMut(&RefCell<ScriptState<C>>) -> bool + Unpin,
+    {
+        WaitFor {
+            state: Rc::clone(&self.state),
+            start_tick: self.state.borrow().tick,
+            condition,
+        }
+    }
+}
+
+/// A future that's ready on the first tick after it was made that the condition is true.
+///
+/// The condition gets the state un-borrowed and borrows it itself,
+/// so it can call back into the `ScriptContext` without a `BorrowMutError`.
+struct WaitFor<C, F> {
+    state: Rc<RefCell<ScriptState<C>>>,
+    start_tick: u64,
+    condition: F,
+}
+
+impl<C, F> Future for WaitFor<C, F>
+where
+    F: FnMut(&RefCell<ScriptState<C>>) -> bool + Unpin,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let started = this.state.borrow().tick > this.start_tick;
+        if started && (this.condition)(&this.state) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Scripts are polled every tick anyways, so nothing needs waking up.
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // SAFETY: none of the functions in the vtable touch the pointer
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_until_condition_can_send() {
+        let mut script = Script::new(|ctx| async move {
+            let inner = ctx.clone();
+            ctx.wait_until(move || {
+                inner.send("from the condition");
+                true
+            })
+            .await;
+            ctx.send("after the wait");
+        });
+
+        // The wait never finishes on the tick it started on
+        assert!(script.run(0.1, |_| false).is_empty());
+        assert_eq!(
+            script.run(0.1, |_| false),
+            vec!["from the condition", "after the wait"]
+        );
+        assert!(script.finished());
+    }
+}
@@ -0,0 +1,83 @@
+//! Helpers for making drawers without copying the whole mode every frame.
+//!
+//! `get_draw_info` runs every frame, and the easy way to write it is `self.clone().into()`.
+//! That's fine for small modes, but anything holding big `Vec`s or `String`s ends up
+//! deep-copying them all every frame even when nothing changed.
+//! Wrapping that state in a `Shared` makes the clone just bump a refcount.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+/// Copy-on-write state shared between a mode and the drawers it hands out.
+///
+/// Cloning one is cheap. Reading it is just a deref.
+/// Writing to it with `make_mut` only copies the inner value if a drawer is still
+/// holding onto the old version, so state that doesn't change is never copied,
+/// and state that does is copied at most once per frame.
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// Do these point at the same value?
+    ///
+    /// Handy for a drawer that wants to know if anything changed since the last frame.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<T: Clone> Shared<T> {
+    /// Get a mutable reference to the value, copying it first if anything else is looking at it.
+    pub fn make_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Saved as just the value inside.
+impl<T: Serialize> Serialize for Shared<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Shared<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
@@ -4,27 +4,31 @@ use anyhow::{bail, Context};
 use macroquad::prelude::{Color, Rect, Texture2D, Vec2, WHITE};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::utils::{
-    draw,
+    draw, save,
     text::{Markup, Wave},
 };
 
 use super::{TextSpan, CHARACTER_COUNT};
 
 /// A box for drawing text and possibly user interaction.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Billboard {
     /// All the pieces of text to be drawn.
     pub text: Vec<TextSpan>,
 
     /// The position of the upper-left corner of the billboard.
+    #[serde(with = "save::vec2")]
     pub pos: Vec2,
     /// The offset the LOWER-left corner of the first character has from
     /// the upper-left corner of the billboard.
+    #[serde(with = "save::vec2")]
     pub offset: Vec2,
 
     /// The patch9 texture used to draw this
+    #[serde(with = "save::texture_key")]
     pub patch9: Texture2D,
     /// The size of the patch9 tile
     pub tile_size: f32,
@@ -56,8 +60,8 @@ impl Billboard {
     }
 
     /// Iterator over characters, slice X's, fonts, and draw positions to draw
-    /// everything on this billboard
-    fn draw_iter(&self) -> impl Iterator<Item = BillboardCharEntry> + '_ {
+    /// everything on this billboard, at the given game time
+    fn draw_iter(&self, time: f64) -> impl Iterator<Item = BillboardCharEntry> + '_ {
         let mut cursor = self.pos + self.offset;
         let sideline = cursor.x;
 
@@ -92,7 +96,7 @@ impl Billboard {
                 let wave_amt = if let Some(wave) = &span.markup.wave {
                     // we do negative because expected behavior is for the wave
                     // to go left to right
-                    let time = macroquad::time::get_time() + (idx as f64 * -wave.transverse);
+                    let time = time + (idx as f64 * -wave.transverse);
                     ((time * TAU / wave.cycle_time) as f32).sin() * wave.magnitude
                 } else {
                     0.0
@@ -118,8 +122,10 @@ impl Billboard {
             })
     }
 
-    /// Draw this to the screen, with the given patch9 background
-    pub fn draw(&self) {
+    /// Draw this to the screen, with the given patch9 background.
+    ///
+    /// `time` is the game time, for animating wavy text.
+    pub fn draw(&self, time: f64) {
         use macroquad::prelude::*;
 
         draw::patch9(
@@ -131,7 +137,7 @@ impl Billboard {
             self.patch9,
         );
 
-        for entry in self.draw_iter() {
+        for entry in self.draw_iter(time) {
             draw_texture_ex(
                 entry.texture,
                 entry.dest_rect.x,
@@ -154,8 +160,15 @@ impl Billboard {
     /// Because this is based on the exact bounds of each character, it's very possible to barely miss clicking on
     /// something, click in-between characters, etc.
     /// So, the distance to a char boundary must be *under* `tolerance` to make it work.
-    pub fn get_char_at_pixel(&self, pos: Vec2, tolerance: f32) -> Option<(usize, usize, u8)> {
-        self.draw_iter().find_map(|entry| {
+    ///
+    /// `time` is the game time, so wavy text is checked where it's drawn.
+    pub fn get_char_at_pixel(
+        &self,
+        pos: Vec2,
+        tolerance: f32,
+        time: f64,
+    ) -> Option<(usize, usize, u8)> {
+        self.draw_iter(time).find_map(|entry| {
             let mut tolerance_rect = entry.dest_rect;
             tolerance_rect.x -= tolerance;
             tolerance_rect.y -= tolerance;
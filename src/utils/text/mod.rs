@@ -5,12 +5,15 @@ pub use billboard::Billboard;
 
 use itertools::*;
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::save;
 
 /// Number of printable characters in an ASCII charset (including the non-printing character).
 pub const CHARACTER_COUNT: usize = 96;
 
 /// A piece of text on a textbox.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextSpan {
     /// The text to be drawn.
     ///
@@ -29,15 +32,17 @@ impl TextSpan {
 }
 
 /// How text is drawn.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Markup {
     /// Font to use.
     ///
     /// Because `Texture2D`s are basically pointers to textures,
     /// it's OK to "copy" them into here.
+    #[serde(with = "save::texture_key")]
     pub font: Texture2D,
 
     /// Color to display the text in
+    #[serde(with = "save::color")]
     pub color: Color,
     /// Space between characters horizontally in pixels
     pub kerning: f32,
@@ -51,7 +56,7 @@ pub struct Markup {
 }
 
 /// Text waves up and down!
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Wave {
     /// A up-and-down cycle takes this many seconds.
     pub cycle_time: f64,
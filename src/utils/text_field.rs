@@ -0,0 +1,117 @@
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Assets,
+    controls::{InputSubscriber, TextInput},
+};
+
+use super::text::{draw_pixel_text, TextAlign, CHARACTER_COUNT};
+
+/// One-line box the player can type into. Good for names and console commands.
+///
+/// Only printable ASCII is accepted, because that's all the fonts have.
+#[derive(Debug, Clone)]
+pub struct TextField {
+    pub bounds: Rect,
+    text: String,
+    /// Index of the char the cursor is before.
+    /// Because everything is ASCII, this is both a byte and a char index.
+    cursor: usize,
+    /// Maximum number of chars allowed
+    max_len: usize,
+}
+
+impl TextField {
+    pub fn new(bounds: Rect, max_len: usize) -> Self {
+        Self {
+            bounds,
+            text: String::new(),
+            cursor: 0,
+            max_len,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text and put the cursor at the end.
+    /// Anything unprintable or past the max length is dropped.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text
+            .chars()
+            .filter(|c| matches!(c, ' '..='~'))
+            .take(self.max_len)
+            .collect();
+        self.cursor = self.text.len();
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Apply everything the player typed this frame.
+    ///
+    /// Returns if the text changed.
+    pub fn update(&mut self, controls: &InputSubscriber) -> bool {
+        let mut changed = false;
+        for input in controls.text_input() {
+            match *input {
+                TextInput::Char(c @ ' '..='~') => {
+                    if self.text.len() < self.max_len {
+                        self.text.insert(self.cursor, c);
+                        self.cursor += 1;
+                        changed = true;
+                    }
+                }
+                TextInput::Char(_) => {}
+                TextInput::Backspace => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.text.remove(self.cursor);
+                        changed = true;
+                    }
+                }
+                TextInput::Delete => {
+                    if self.cursor < self.text.len() {
+                        self.text.remove(self.cursor);
+                        changed = true;
+                    }
+                }
+                TextInput::Left => self.cursor = self.cursor.saturating_sub(1),
+                TextInput::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+                TextInput::Home => self.cursor = 0,
+                TextInput::End => self.cursor = self.text.len(),
+            }
+        }
+        changed
+    }
+
+    /// Quick-and-dirty drawing, in the same style as `Button`.
+    /// Draws the text and cursor using the border color.
+    pub fn draw(&self, color: Color, border: Color, border_width: f32, assets: &Assets) {
+        let font = assets.textures.fonts.small;
+        let char_width = font.width() / CHARACTER_COUNT as f32;
+
+        let x = self.bounds.x.round();
+        let y = self.bounds.y.round();
+        let w = self.bounds.w.round();
+        let h = self.bounds.h.round();
+        draw_rectangle(x, y, w, h, color);
+        draw_rectangle_lines(x, y, w, h, border_width, border);
+
+        let tx = x + 2.0;
+        let ty = (y + h / 2.0 - 2.5).round();
+        draw_pixel_text(&self.text, tx, ty, TextAlign::Left, border, font);
+
+        let cursor_x = tx + self.cursor as f32 * (char_width + 1.0) - 1.0;
+        draw_line(
+            cursor_x,
+            ty - 1.0,
+            cursor_x,
+            ty + font.height() + 1.0,
+            1.0,
+            border,
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! Drawing tile layers a chunk at a time.
+//!
+//! Each layer is cut into square chunks of `CHUNK_TILES` tiles. The first time a chunk
+//! comes into view, the quads for all of its tiles are worked out once and kept as a mesh
+//! per tileset, so drawing it after that is a `draw_mesh` per tileset however many tiles it has.
+//! `TileLayer::set` throws away the chunk it changed, to be built again next time it's drawn.
+//!
+//! Animated tiles change every frame, so they're left out of the meshes and drawn fresh each time.
+
+use macroquad::{models::Vertex, prelude::*};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, PoisonError},
+};
+
+use super::{
+    Layer, TileLayer, Tilemap, FLIPPED_DIAGONALLY, FLIPPED_HORIZONTALLY, FLIPPED_VERTICALLY,
+};
+
+/// How many tiles across and down a chunk is.
+pub const CHUNK_TILES: u32 = 16;
+
+/// Most quads to put in one mesh, to stay under macroquad's limit on indices per draw call.
+const MAX_QUADS: usize = 800;
+
+/// The meshes for the chunks of a layer that have been drawn so far.
+///
+/// Cloning one gives an empty cache; the clone builds its own chunks when it's drawn.
+#[derive(Default)]
+pub(super) struct ChunkCache {
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    /// The offset and opacity of the layer when the chunks were built.
+    /// If either changes, all the chunks are built again.
+    built_with: Option<(Vec2, f32)>,
+    chunks: HashMap<(u32, u32), Chunk>,
+}
+
+/// Everything it takes to draw one chunk.
+struct Chunk {
+    meshes: Meshes,
+    /// The animated tiles in the chunk, as their coordinates and global ID
+    animated: Vec<(u32, u32, u32)>,
+}
+
+impl ChunkCache {
+    /// Forget the chunk a tile is in.
+    pub fn forget_tile(&mut self, x: u32, y: u32) {
+        self.inner_mut()
+            .chunks
+            .remove(&(x / CHUNK_TILES, y / CHUNK_TILES));
+    }
+
+    /// Forget all the chunks.
+    pub fn clear(&mut self) {
+        self.inner_mut().chunks.clear();
+    }
+
+    fn inner_mut(&mut self) -> &mut CacheInner {
+        self.inner.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for ChunkCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for ChunkCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChunkCache")
+    }
+}
+
+/// Draw the chunks of a tile layer that are inside `view`.
+pub(super) fn draw_layer(map: &Tilemap, layer: &Layer, tiles: &TileLayer, view: Rect, time: f64) {
+    let tint = Color::new(1.0, 1.0, 1.0, layer.opacity);
+    let mut cache = tiles
+        .chunks
+        .inner
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let built_with = Some((layer.offset, layer.opacity));
+    if cache.built_with != built_with {
+        cache.chunks.clear();
+        cache.built_with = built_with;
+    }
+
+    // Tiles taller or wider than the grid hang off the top and right of their cell,
+    // so look a little further out for ones that poke into view.
+    let overhang = map.tilesets.iter().fold(Vec2::ZERO, |acc, tileset| {
+        acc.max(vec2(
+            tileset.tile_width.saturating_sub(map.tile_width) as f32,
+            tileset.tile_height.saturating_sub(map.tile_height) as f32,
+        ))
+    });
+    let top_left = vec2(view.x, view.y) - layer.offset - vec2(overhang.x, 0.0);
+    let bottom_right = vec2(view.right(), view.bottom()) - layer.offset + vec2(0.0, overhang.y);
+    let (x0, y0) = map.pixel_to_tile(top_left);
+    let (x1, y1) = map.pixel_to_tile(bottom_right);
+    let x1 = x1.min(tiles.width as i32 - 1);
+    let y1 = y1.min(tiles.height as i32 - 1);
+    if x1 < 0 || y1 < 0 {
+        return;
+    }
+    let (x0, y0) = (x0.max(0) as u32, y0.max(0) as u32);
+    let (x1, y1) = (x1 as u32, y1 as u32);
+
+    let mut animated = Meshes::default();
+    for cy in y0 / CHUNK_TILES..=y1 / CHUNK_TILES {
+        for cx in x0 / CHUNK_TILES..=x1 / CHUNK_TILES {
+            let chunk = cache
+                .chunks
+                .entry((cx, cy))
+                .or_insert_with(|| build_chunk(map, layer, tiles, (cx, cy), tint));
+            chunk.meshes.draw();
+            for &(x, y, gid) in chunk.animated.iter() {
+                push_tile(&mut animated, map, layer, (x, y), gid, time, tint);
+            }
+        }
+    }
+    animated.draw();
+}
+
+fn build_chunk(
+    map: &Tilemap,
+    layer: &Layer,
+    tiles: &TileLayer,
+    (cx, cy): (u32, u32),
+    tint: Color,
+) -> Chunk {
+    let mut chunk = Chunk {
+        meshes: Meshes::default(),
+        animated: Vec::new(),
+    };
+    let xs = cx * CHUNK_TILES..((cx + 1) * CHUNK_TILES).min(tiles.width);
+    let ys = cy * CHUNK_TILES..((cy + 1) * CHUNK_TILES).min(tiles.height);
+    for y in ys {
+        for x in xs.clone() {
+            let gid = tiles.raw(x, y);
+            let animated = match map.tileset_of(gid) {
+                Some((tileset, id)) => tileset
+                    .tiles
+                    .get(&id)
+                    .is_some_and(|tile| !tile.animation.is_empty()),
+                None => continue,
+            };
+            if animated {
+                chunk.animated.push((x, y, gid));
+            } else {
+                push_tile(&mut chunk.meshes, map, layer, (x, y), gid, 0.0, tint);
+            }
+        }
+    }
+    chunk
+}
+
+/// Add the quad for one tile, as it looks at `time`.
+fn push_tile(
+    meshes: &mut Meshes,
+    map: &Tilemap,
+    layer: &Layer,
+    (x, y): (u32, u32),
+    gid: u32,
+    time: f64,
+    tint: Color,
+) {
+    let (tileset, id) = match map.tileset_of(gid) {
+        Some(it) => it,
+        None => return,
+    };
+    let texture = tileset.texture.unwrap_or_else(|| {
+        panic!(
+            "tileset {} was drawn before its image was loaded",
+            tileset.name
+        )
+    });
+    let id = tileset.animated_id(id, time);
+
+    // Tiles sit on the bottom-left corner of their cell
+    let corner = map.tile_to_pixel(x as i32, y as i32)
+        + layer.offset
+        + vec2(0.0, map.tile_height as f32 - tileset.tile_height as f32);
+    let dest = Rect::new(
+        corner.x.round(),
+        corner.y.round(),
+        tileset.tile_width as f32,
+        tileset.tile_height as f32,
+    );
+    let quad = tile_quad(
+        dest,
+        tileset.source_rect(id),
+        vec2(texture.width(), texture.height()),
+        flip_params(gid),
+        tint,
+    );
+    meshes.push(texture, quad);
+}
+
+/// Quads sorted into meshes by texture.
+#[derive(Default)]
+struct Meshes(Vec<Mesh>);
+
+impl Meshes {
+    fn push(&mut self, texture: Texture2D, quad: [Vertex; 4]) {
+        let has_room =
+            |mesh: &&mut Mesh| mesh.texture == Some(texture) && mesh.vertices.len() < MAX_QUADS * 4;
+        let mesh = match self.0.iter_mut().find(has_room) {
+            Some(mesh) => mesh,
+            None => {
+                self.0.push(Mesh {
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                    texture: Some(texture),
+                });
+                self.0.last_mut().unwrap()
+            }
+        };
+        let base = mesh.vertices.len() as u16;
+        mesh.vertices.extend_from_slice(&quad);
+        mesh.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    fn draw(&self) {
+        for mesh in self.0.iter() {
+            draw_mesh(mesh);
+        }
+    }
+}
+
+/// How to draw a tile with the flip flags in its global ID:
+/// whether to flip it across, whether to flip it down, and how far to turn it.
+///
+/// Tiled flips diagonally first, then horizontally, then vertically.
+/// A diagonal flip is a vertical flip then a quarter turn clockwise,
+/// and `tile_quad` flips before it turns, so the other flips swap axes.
+fn flip_params(gid: u32) -> (bool, bool, f32) {
+    let flip_h = gid & FLIPPED_HORIZONTALLY != 0;
+    let flip_v = gid & FLIPPED_VERTICALLY != 0;
+    if gid & FLIPPED_DIAGONALLY != 0 {
+        (flip_v, !flip_h, std::f32::consts::FRAC_PI_2)
+    } else {
+        (flip_h, flip_v, 0.0)
+    }
+}
+
+/// The corners of a tile's quad, clockwise from the top-left of the image.
+///
+/// This does the same thing as `draw_texture_ex`: flip, then turn around the middle.
+fn tile_quad(
+    dest: Rect,
+    source: Rect,
+    texture_size: Vec2,
+    (flip_x, flip_y, rotation): (bool, bool, f32),
+    color: Color,
+) -> [Vertex; 4] {
+    let (mut x, mut y, mut w, mut h) = (dest.x, dest.y, dest.w, dest.h);
+    if flip_x {
+        x += w;
+        w = -w;
+    }
+    if flip_y {
+        y += h;
+        h = -h;
+    }
+    let pivot = vec2(x + w / 2.0, y + h / 2.0);
+    let (sin, cos) = rotation.sin_cos();
+    let corner = |pos: Vec2, uv: Vec2| {
+        let pos = pos - pivot;
+        let pos = vec2(pos.x * cos - pos.y * sin, pos.x * sin + pos.y * cos) + pivot;
+        Vertex {
+            position: vec3(pos.x, pos.y, 0.0),
+            uv: uv / texture_size,
+            color,
+        }
+    };
+    [
+        corner(vec2(x, y), vec2(source.x, source.y)),
+        corner(vec2(x + w, y), vec2(source.right(), source.y)),
+        corner(vec2(x + w, y + h), vec2(source.right(), source.bottom())),
+        corner(vec2(x, y + h), vec2(source.x, source.bottom())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tilemap::{AnimationFrame, LayerKind, Properties, TileData, Tileset};
+
+    /// A map with one 20x20 layer that's all tile 1, and an animated tile 2.
+    fn map() -> Tilemap {
+        let mut tiles = HashMap::new();
+        tiles.insert(
+            1,
+            TileData {
+                properties: Properties::default(),
+                animation: vec![AnimationFrame {
+                    tile_id: 0,
+                    secs: 1.0,
+                }],
+            },
+        );
+        let tileset = Tileset {
+            first_gid: 1,
+            name: "tiles".to_owned(),
+            tile_width: 16,
+            tile_height: 16,
+            tile_count: 2,
+            columns: 2,
+            margin: 0,
+            spacing: 0,
+            image: String::new(),
+            texture: Some(Texture2D::empty()),
+            tiles,
+            properties: Properties::default(),
+        };
+        Tilemap {
+            width: 20,
+            height: 20,
+            tile_width: 16,
+            tile_height: 16,
+            tilesets: vec![tileset],
+            layers: vec![Layer {
+                name: "ground".to_owned(),
+                visible: true,
+                opacity: 1.0,
+                offset: Vec2::ZERO,
+                properties: Properties::default(),
+                kind: LayerKind::Tiles(TileLayer::new(20, 20, vec![1; 400]).unwrap()),
+            }],
+            properties: Properties::default(),
+        }
+    }
+
+    fn quads(chunk: &Chunk) -> usize {
+        chunk
+            .meshes
+            .0
+            .iter()
+            .map(|mesh| mesh.vertices.len() / 4)
+            .sum()
+    }
+
+    #[test]
+    fn chunks_cover_the_layer() {
+        let mut map = map();
+        map.tile_layer_mut("ground").unwrap().set(3, 4, 2);
+        let layer = &map.layers[0];
+        let tiles = map.tile_layer("ground").unwrap();
+
+        let full = build_chunk(&map, layer, tiles, (0, 0), WHITE);
+        assert_eq!(quads(&full), 16 * 16 - 1);
+        assert_eq!(full.animated, vec![(3, 4, 2)]);
+
+        // The chunks on the edges only go as far as the layer does
+        let edge = build_chunk(&map, layer, tiles, (1, 1), WHITE);
+        assert_eq!(quads(&edge), 4 * 4);
+        assert!(edge.animated.is_empty());
+    }
+
+    #[test]
+    fn setting_a_tile_forgets_its_chunk() {
+        let map = map();
+        let mut tiles = map.tile_layer("ground").unwrap().clone();
+        for chunk in [(0, 0), (1, 0)] {
+            let built = build_chunk(&map, &map.layers[0], &tiles, chunk, WHITE);
+            tiles.chunks.inner_mut().chunks.insert(chunk, built);
+        }
+
+        tiles.set(17, 2, 0);
+        let cached = &tiles.chunks.inner_mut().chunks;
+        assert!(cached.contains_key(&(0, 0)));
+        assert!(!cached.contains_key(&(1, 0)));
+    }
+
+    /// Where Tiled puts a point on the tile's image, going by the spec.
+    fn tiled_flip(gid: u32, mut point: Vec2) -> Vec2 {
+        if gid & FLIPPED_DIAGONALLY != 0 {
+            point = vec2(point.y, point.x);
+        }
+        if gid & FLIPPED_HORIZONTALLY != 0 {
+            point.x = 1.0 - point.x;
+        }
+        if gid & FLIPPED_VERTICALLY != 0 {
+            point.y = 1.0 - point.y;
+        }
+        point
+    }
+
+    #[test]
+    fn flips_match_tiled() {
+        let unit = Rect::new(0.0, 0.0, 1.0, 1.0);
+        for flags in 0..8 {
+            let gid = (flags << 29) | 1;
+            let quad = tile_quad(unit, unit, Vec2::ONE, flip_params(gid), WHITE);
+            for vertex in quad.iter() {
+                let pos = vec2(vertex.position.x, vertex.position.y);
+                let expected = tiled_flip(gid, vertex.uv);
+                assert!(
+                    (pos - expected).length() < 0.001,
+                    "flags {:03b}: corner {:?} of the image went to {:?}, not {:?}",
+                    flags,
+                    vertex.uv,
+                    pos,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Reading Tiled's JSON format.
+
+use anyhow::{anyhow, bail};
+use macroquad::prelude::*;
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+use super::{
+    decode_tiles, parse_property, AnimationFrame, Layer, LayerKind, Object, ObjectShape,
+    Properties, Property, TileData, TileLayer, Tilemap, Tileset, TilesetSource,
+};
+
+pub fn parse_map(text: &str) -> anyhow::Result<(Tilemap, Vec<TilesetSource>)> {
+    let json: Value = serde_json::from_str(text)?;
+    if json["infinite"].as_bool() == Some(true) {
+        bail!("infinite maps aren't supported");
+    }
+
+    let mut tilesets = Vec::new();
+    for tileset in array(&json, "tilesets")? {
+        let first_gid = uint(tileset, "firstgid")?;
+        tilesets.push(match tileset["source"].as_str() {
+            Some(path) => TilesetSource::External {
+                first_gid,
+                path: path.to_owned(),
+            },
+            None => TilesetSource::Embedded(parse_tileset_value(tileset, first_gid)?),
+        });
+    }
+
+    let mut layers = Vec::new();
+    parse_layers(array(&json, "layers")?, Vec2::ZERO, &mut layers)?;
+
+    let map = Tilemap {
+        width: uint(&json, "width")?,
+        height: uint(&json, "height")?,
+        tile_width: uint(&json, "tilewidth")?,
+        tile_height: uint(&json, "tileheight")?,
+        tilesets: Vec::new(),
+        layers,
+        properties: properties(&json)?,
+    };
+    Ok((map, tilesets))
+}
+
+pub fn parse_tileset(text: &str, first_gid: u32) -> anyhow::Result<Tileset> {
+    let json: Value = serde_json::from_str(text)?;
+    parse_tileset_value(&json, first_gid)
+}
+
+fn parse_tileset_value(json: &Value, first_gid: u32) -> anyhow::Result<Tileset> {
+    let mut tiles = HashMap::new();
+    if let Some(tile_list) = json["tiles"].as_array() {
+        for tile in tile_list {
+            let mut animation = Vec::new();
+            if let Some(frames) = tile["animation"].as_array() {
+                for frame in frames {
+                    animation.push(AnimationFrame {
+                        tile_id: uint(frame, "tileid")?,
+                        secs: uint(frame, "duration")? as f32 / 1000.0,
+                    });
+                }
+            }
+            tiles.insert(
+                uint(tile, "id")?,
+                TileData {
+                    properties: properties(tile)?,
+                    animation,
+                },
+            );
+        }
+    }
+
+    Ok(Tileset {
+        first_gid,
+        name: string(json, "name"),
+        tile_width: uint(json, "tilewidth")?,
+        tile_height: uint(json, "tileheight")?,
+        tile_count: uint(json, "tilecount")?,
+        columns: uint(json, "columns")?,
+        margin: json["margin"].as_u64().unwrap_or(0) as u32,
+        spacing: json["spacing"].as_u64().unwrap_or(0) as u32,
+        image: json["image"]
+            .as_str()
+            .ok_or_else(|| anyhow!("only tilesets with one image are supported"))?
+            .to_owned(),
+        texture: None,
+        tiles,
+        properties: properties(json)?,
+    })
+}
+
+/// Read layers into `out`, flattening groups. `offset` is the offset of the group they're in.
+fn parse_layers(layers: &[Value], offset: Vec2, out: &mut Vec<Layer>) -> anyhow::Result<()> {
+    for layer in layers {
+        let offset = offset
+            + vec2(
+                layer["offsetx"].as_f64().unwrap_or(0.0) as f32,
+                layer["offsety"].as_f64().unwrap_or(0.0) as f32,
+            );
+        let kind = match layer["type"].as_str() {
+            Some("tilelayer") => {
+                let width = uint(layer, "width")?;
+                let height = uint(layer, "height")?;
+                let data = match &layer["data"] {
+                    Value::String(text) => decode_tiles(
+                        text,
+                        layer["encoding"].as_str().unwrap_or("base64"),
+                        layer["compression"].as_str().unwrap_or(""),
+                    )?,
+                    Value::Array(nums) => nums
+                        .iter()
+                        .map(|num| {
+                            num.as_u64()
+                                .map(|num| num as u32)
+                                .ok_or_else(|| anyhow!("bad tile {}", num))
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                    _ => bail!("tile layers need data"),
+                };
+                LayerKind::Tiles(TileLayer::new(width, height, data)?)
+            }
+            Some("objectgroup") => LayerKind::Objects(
+                array(layer, "objects")?
+                    .iter()
+                    .map(parse_object)
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            Some("group") => {
+                parse_layers(array(layer, "layers")?, offset, out)?;
+                continue;
+            }
+            // Image layers
+            _ => continue,
+        };
+
+        out.push(Layer {
+            name: string(layer, "name"),
+            visible: layer["visible"].as_bool().unwrap_or(true),
+            opacity: layer["opacity"].as_f64().unwrap_or(1.0) as f32,
+            offset,
+            properties: properties(layer)?,
+            kind,
+        });
+    }
+    Ok(())
+}
+
+fn parse_object(json: &Value) -> anyhow::Result<Object> {
+    let points = |key: &str| -> anyhow::Result<Vec<Vec2>> {
+        array(json, key)?
+            .iter()
+            .map(|point| Ok(vec2(float(point, "x")?, float(point, "y")?)))
+            .collect()
+    };
+    let shape = if json["ellipse"].as_bool() == Some(true) {
+        ObjectShape::Ellipse
+    } else if json["point"].as_bool() == Some(true) {
+        ObjectShape::Point
+    } else if json["polygon"].is_array() {
+        ObjectShape::Polygon(points("polygon")?)
+    } else if json["polyline"].is_array() {
+        ObjectShape::Polyline(points("polyline")?)
+    } else {
+        ObjectShape::Rect
+    };
+
+    // Newer versions of Tiled call `type` `class`
+    let kind = match json["class"].as_str() {
+        Some(class) => class.to_owned(),
+        None => string(json, "type"),
+    };
+
+    Ok(Object {
+        id: uint(json, "id")?,
+        name: string(json, "name"),
+        kind,
+        pos: vec2(float(json, "x")?, float(json, "y")?),
+        size: vec2(
+            json["width"].as_f64().unwrap_or(0.0) as f32,
+            json["height"].as_f64().unwrap_or(0.0) as f32,
+        ),
+        rotation: json["rotation"].as_f64().unwrap_or(0.0) as f32,
+        gid: json["gid"].as_u64().map(|gid| gid as u32),
+        visible: json["visible"].as_bool().unwrap_or(true),
+        shape,
+        properties: properties(json)?,
+    })
+}
+
+fn properties(json: &Value) -> anyhow::Result<Properties> {
+    let mut out = HashMap::new();
+    if let Some(props) = json["properties"].as_array() {
+        for prop in props {
+            let name = string(prop, "name");
+            let kind = prop["type"].as_str().unwrap_or("string");
+            let value = match &prop["value"] {
+                Value::Bool(it) => Property::Bool(*it),
+                Value::String(it) => parse_property(kind, it)?,
+                Value::Number(num) => match kind {
+                    "int" => Property::Int(num.as_i64().unwrap_or(0)),
+                    "object" => Property::Object(num.as_u64().unwrap_or(0) as u32),
+                    _ => Property::Float(num.as_f64().unwrap_or(0.0)),
+                },
+                // Class properties
+                _ => continue,
+            };
+            out.insert(name, value);
+        }
+    }
+    Ok(Properties(out))
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> anyhow::Result<&'a [Value]> {
+    json[key]
+        .as_array()
+        .map(|it| it.as_slice())
+        .ok_or_else(|| anyhow!("missing {:?}", key))
+}
+
+fn uint(json: &Value, key: &str) -> anyhow::Result<u32> {
+    json[key]
+        .as_u64()
+        .map(|it| it as u32)
+        .ok_or_else(|| anyhow!("missing {:?}", key))
+}
+
+fn float(json: &Value, key: &str) -> anyhow::Result<f32> {
+    json[key]
+        .as_f64()
+        .map(|it| it as f32)
+        .ok_or_else(|| anyhow!("missing {:?}", key))
+}
+
+fn string(json: &Value, key: &str) -> String {
+    json[key].as_str().unwrap_or_default().to_owned()
+}
@@ -0,0 +1,515 @@
+//! Tile maps made in [Tiled](https://www.mapeditor.org/).
+//!
+//! Maps are loaded with the rest of the assets; see `assets::Maps`.
+//! Both `.tmx` and `.json` maps work, with embedded or external tilesets,
+//! and tile data in CSV or base64 (optionally zlib or gzip compressed).
+//! Infinite maps, image layers and zstd compression aren't supported.
+//!
+//! One map pixel is one world unit, so under a `GameCamera` at zoom 1
+//! one map pixel is one canvas pixel.
+
+mod chunks;
+mod json;
+mod tmx;
+
+use macroquad::prelude::*;
+
+use std::{collections::HashMap, path::Path};
+
+use self::chunks::ChunkCache;
+use crate::{HEIGHT, WIDTH};
+
+/// Tile IDs in layers have these bits set when the tile is flipped.
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY;
+
+/// Which kind of file a map or tileset is in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MapFormat {
+    Tmx,
+    Json,
+}
+
+impl MapFormat {
+    /// Guess the format from the file extension.
+    pub fn of(path: &str) -> Option<MapFormat> {
+        match Path::new(path).extension()?.to_str()? {
+            "tmx" | "tsx" | "xml" => Some(MapFormat::Tmx),
+            "json" | "tmj" | "tsj" => Some(MapFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A whole map.
+#[derive(Debug, Clone)]
+pub struct Tilemap {
+    /// Size in tiles
+    pub width: u32,
+    pub height: u32,
+    /// Size of one grid cell in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+
+    /// Sorted by `first_gid`
+    pub tilesets: Vec<Tileset>,
+    /// From the bottom up. Layers inside groups are flattened out into this.
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+}
+
+impl Tilemap {
+    /// Read a map from the text of a file.
+    ///
+    /// External tilesets are left for the caller to load;
+    /// `assets::tilemap` does that and loads all the tileset textures too.
+    pub fn parse(text: &str, format: MapFormat) -> anyhow::Result<(Tilemap, Vec<TilesetSource>)> {
+        match format {
+            MapFormat::Tmx => tmx::parse_map(text),
+            MapFormat::Json => json::parse_map(text),
+        }
+    }
+
+    /// Size of the whole map in pixels.
+    pub fn pixel_size(&self) -> Vec2 {
+        vec2(
+            (self.width * self.tile_width) as f32,
+            (self.height * self.tile_height) as f32,
+        )
+    }
+
+    /// The whole map in pixels, for `GameCamera::bounds`.
+    pub fn pixel_rect(&self) -> Rect {
+        let size = self.pixel_size();
+        Rect::new(0.0, 0.0, size.x, size.y)
+    }
+
+    /// The top-left corner of the tile at these coordinates, in pixels.
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> Vec2 {
+        vec2(
+            (x * self.tile_width as i32) as f32,
+            (y * self.tile_height as i32) as f32,
+        )
+    }
+
+    /// The coordinates of the tile this pixel is in. Can be outside the map.
+    pub fn pixel_to_tile(&self, pos: Vec2) -> (i32, i32) {
+        (
+            (pos.x / self.tile_width as f32).floor() as i32,
+            (pos.y / self.tile_height as f32).floor() as i32,
+        )
+    }
+
+    /// How many tiles fit on the canvas at once, rounding up.
+    pub fn tiles_per_canvas(&self) -> (u32, u32) {
+        (
+            (WIDTH / self.tile_width as f32).ceil() as u32,
+            (HEIGHT / self.tile_height as f32).ceil() as u32,
+        )
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        match &self.layer(name)?.kind {
+            LayerKind::Tiles(tiles) => Some(tiles),
+            _ => None,
+        }
+    }
+
+    pub fn tile_layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        match &mut self.layer_mut(name)?.kind {
+            LayerKind::Tiles(tiles) => Some(tiles),
+            _ => None,
+        }
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&[Object]> {
+        match &self.layer(name)?.kind {
+            LayerKind::Objects(objects) => Some(objects),
+            _ => None,
+        }
+    }
+
+    /// Find the tileset a global tile ID belongs to, and the tile's ID inside it.
+    pub fn tileset_of(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        let gid = gid & !FLIP_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        let tileset = self
+            .tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)?;
+        let id = gid - tileset.first_gid;
+        if id < tileset.tile_count {
+            Some((tileset, id))
+        } else {
+            None
+        }
+    }
+
+    /// The custom properties on a tile, if it has any.
+    pub fn tile_properties(&self, gid: u32) -> Option<&Properties> {
+        let (tileset, id) = self.tileset_of(gid)?;
+        tileset.tiles.get(&id).map(|tile| &tile.properties)
+    }
+
+    /// Draw every visible tile layer, from the bottom up.
+    ///
+    /// Only the chunks inside `view` are drawn; pass `GameCamera::view_rect` and
+    /// draw this inside `GameCamera::draw_with`. `time` is for animated tiles.
+    /// Each chunk is only worked out the first time it's seen; see `chunks`.
+    pub fn draw(&self, view: Rect, time: f64) {
+        for idx in 0..self.layers.len() {
+            self.draw_layer(idx, view, time);
+        }
+    }
+
+    /// Draw one layer, if it's a visible tile layer, so things can be drawn between layers.
+    pub fn draw_layer(&self, idx: usize, view: Rect, time: f64) {
+        let layer = &self.layers[idx];
+        if let LayerKind::Tiles(tiles) = &layer.kind {
+            if layer.visible {
+                chunks::draw_layer(self, layer, tiles, view, time);
+            }
+        }
+    }
+
+    /// Throw away every layer's cached chunks.
+    ///
+    /// Editing tiles with `TileLayer::set` takes care of itself,
+    /// but changing the tilesets after the map has been drawn needs this.
+    pub fn clear_chunks(&mut self) {
+        for layer in self.layers.iter_mut() {
+            if let LayerKind::Tiles(tiles) = &mut layer.kind {
+                tiles.chunks.clear();
+            }
+        }
+    }
+}
+
+/// A tileset the map refers to, which might be in another file.
+#[derive(Debug, Clone)]
+pub enum TilesetSource {
+    Embedded(Tileset),
+    /// In another file, with a path relative to the map.
+    External {
+        first_gid: u32,
+        path: String,
+    },
+}
+
+/// A sheet of tiles.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    /// The global ID of this tileset's first tile
+    pub first_gid: u32,
+    pub name: String,
+    /// Size of each tile in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    /// Pixels around the edge of the image
+    pub margin: u32,
+    /// Pixels between tiles
+    pub spacing: u32,
+    /// Path to the image, relative to the file the tileset is in
+    pub image: String,
+    /// `None` until the image is loaded, which `assets::tilemap` does
+    pub texture: Option<Texture2D>,
+    /// Extra info about tiles that have any, by their ID in this tileset
+    pub tiles: HashMap<u32, TileData>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    /// Read a tileset from the text of a `.tsx` or `.json` file.
+    pub fn parse(text: &str, format: MapFormat, first_gid: u32) -> anyhow::Result<Tileset> {
+        match format {
+            MapFormat::Tmx => tmx::parse_tileset(text, first_gid),
+            MapFormat::Json => json::parse_tileset(text, first_gid),
+        }
+    }
+
+    /// Where a tile is on the image.
+    pub fn source_rect(&self, id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (col, row) = (id % columns, id / columns);
+        Rect::new(
+            (self.margin + col * (self.tile_width + self.spacing)) as f32,
+            (self.margin + row * (self.tile_height + self.spacing)) as f32,
+            self.tile_width as f32,
+            self.tile_height as f32,
+        )
+    }
+
+    /// Which tile to show for this one at this time, if it's animated.
+    pub fn animated_id(&self, id: u32, time: f64) -> u32 {
+        let frames = match self.tiles.get(&id) {
+            Some(tile) if !tile.animation.is_empty() => &tile.animation,
+            _ => return id,
+        };
+        let total: f64 = frames.iter().map(|frame| frame.secs as f64).sum();
+        if total <= 0.0 {
+            return id;
+        }
+        let mut time = time.rem_euclid(total);
+        for frame in frames {
+            if time < frame.secs as f64 {
+                return frame.tile_id;
+            }
+            time -= frame.secs as f64;
+        }
+        frames.last().unwrap().tile_id
+    }
+}
+
+/// Extra info about one tile in a tileset.
+#[derive(Debug, Clone, Default)]
+pub struct TileData {
+    pub properties: Properties,
+    /// Empty if it's not animated
+    pub animation: Vec<AnimationFrame>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationFrame {
+    /// ID of the tile in the same tileset to show
+    pub tile_id: u32,
+    pub secs: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// How far the layer is drawn from where it would be, in pixels
+    pub offset: Vec2,
+    pub properties: Properties,
+    pub kind: LayerKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum LayerKind {
+    Tiles(TileLayer),
+    Objects(Vec<Object>),
+}
+
+/// A grid of global tile IDs. 0 means no tile.
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row, with the flip flags still in
+    data: Vec<u32>,
+    chunks: ChunkCache,
+}
+
+impl TileLayer {
+    pub fn new(width: u32, height: u32, data: Vec<u32>) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            data.len() == (width * height) as usize,
+            "layer is {}x{} but has {} tiles",
+            width,
+            height,
+            data.len()
+        );
+        Ok(Self {
+            width,
+            height,
+            data,
+            chunks: ChunkCache::default(),
+        })
+    }
+
+    /// The global tile ID at these coordinates, without flip flags.
+    /// `None` if it's off the layer; `Some(0)` if it's empty.
+    pub fn get(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            None
+        } else {
+            Some(self.raw(x as u32, y as u32) & !FLIP_FLAGS)
+        }
+    }
+
+    /// Put a tile down, or 0 to clear it. Does nothing off the layer.
+    pub fn set(&mut self, x: i32, y: i32, gid: u32) {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            self.data[(y as u32 * self.width + x as u32) as usize] = gid;
+            self.chunks.forget_tile(x as u32, y as u32);
+        }
+    }
+
+    fn raw(&self, x: u32, y: u32) -> u32 {
+        self.data[(y * self.width + x) as usize]
+    }
+}
+
+/// Something placed on an object layer.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub id: u32,
+    pub name: String,
+    /// The object's type (called class in newer versions of Tiled)
+    pub kind: String,
+    /// In pixels. For tile objects this is the bottom-left corner, like in Tiled.
+    pub pos: Vec2,
+    pub size: Vec2,
+    /// Clockwise, in degrees
+    pub rotation: f32,
+    /// For tile objects, the global ID of the tile
+    pub gid: Option<u32>,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone)]
+pub enum ObjectShape {
+    Rect,
+    Ellipse,
+    Point,
+    /// Points relative to the object's position
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+}
+
+/// A custom property's value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Color),
+    /// A path relative to the map
+    File(String),
+    /// The ID of an object
+    Object(u32),
+}
+
+/// Custom properties, by name.
+#[derive(Debug, Clone, Default)]
+pub struct Properties(pub HashMap<String, Property>);
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.0.get(name)
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Property::Bool(it) => Some(*it),
+            _ => None,
+        }
+    }
+
+    /// Ints, or whole floats.
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Property::Int(it) => Some(*it),
+            Property::Float(it) if it.fract() == 0.0 => Some(*it as i64),
+            _ => None,
+        }
+    }
+
+    /// Floats, or ints.
+    pub fn float(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Property::Float(it) => Some(*it),
+            Property::Int(it) => Some(*it as f64),
+            _ => None,
+        }
+    }
+
+    /// Strings or file paths.
+    pub fn str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Property::String(it) | Property::File(it) => Some(it),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Option<Color> {
+        match self.get(name)? {
+            Property::Color(it) => Some(*it),
+            _ => None,
+        }
+    }
+}
+
+/// Turn a property from its type and value as written in the file.
+fn parse_property(kind: &str, value: &str) -> anyhow::Result<Property> {
+    Ok(match kind {
+        "bool" => Property::Bool(value == "true"),
+        "int" => Property::Int(value.parse()?),
+        "float" => Property::Float(value.parse()?),
+        "color" => Property::Color(parse_color(value)?),
+        "file" => Property::File(value.to_owned()),
+        "object" => Property::Object(value.parse()?),
+        _ => Property::String(value.to_owned()),
+    })
+}
+
+/// Tiled writes colors as `#AARRGGBB` or `#RRGGBB`.
+fn parse_color(text: &str) -> anyhow::Result<Color> {
+    let hex = text.trim_start_matches('#');
+    if hex.is_empty() {
+        // Unset colors are blank
+        return Ok(Color::new(0.0, 0.0, 0.0, 0.0));
+    }
+    let code = u32::from_str_radix(hex, 16)?;
+    let [a, r, g, b] = match hex.len() {
+        8 => code.to_be_bytes(),
+        6 => (code | 0xff00_0000).to_be_bytes(),
+        _ => anyhow::bail!("bad color {:?}", text),
+    };
+    Ok(Color::from_rgba(r, g, b, a))
+}
+
+/// Unpack a layer's tile data, written as CSV or base64 with optional compression.
+fn decode_tiles(text: &str, encoding: &str, compression: &str) -> anyhow::Result<Vec<u32>> {
+    use std::io::Read;
+
+    match encoding {
+        "csv" => text.split(',').map(|num| Ok(num.trim().parse()?)).collect(),
+        "base64" => {
+            let packed = base64::decode(text.trim())?;
+            let bytes = match compression {
+                "" => packed,
+                "zlib" => {
+                    let mut out = Vec::new();
+                    flate2::read::ZlibDecoder::new(&packed[..]).read_to_end(&mut out)?;
+                    out
+                }
+                "gzip" => {
+                    let mut out = Vec::new();
+                    flate2::read::GzDecoder::new(&packed[..]).read_to_end(&mut out)?;
+                    out
+                }
+                _ => anyhow::bail!("unsupported compression {:?}", compression),
+            };
+            anyhow::ensure!(
+                bytes.len() % 4 == 0,
+                "tile data isn't a whole number of tiles"
+            );
+            Ok(bytes
+                .chunks(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        _ => anyhow::bail!("unsupported encoding {:?}", encoding),
+    }
+}
@@ -0,0 +1,289 @@
+//! Reading Tiled's XML formats, `.tmx` for maps and `.tsx` for tilesets.
+
+use anyhow::{anyhow, bail};
+use macroquad::prelude::*;
+use xml::reader::{EventReader, XmlEvent};
+
+use std::{collections::HashMap, str::FromStr};
+
+use super::{
+    decode_tiles, parse_property, AnimationFrame, Layer, LayerKind, Object, ObjectShape,
+    Properties, TileData, TileLayer, Tilemap, Tileset, TilesetSource,
+};
+
+/// Just enough of an XML tree to walk through.
+struct Element {
+    name: String,
+    attrs: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn parse(text: &str) -> anyhow::Result<Element> {
+        let mut stack: Vec<Element> = Vec::new();
+        for event in EventReader::from_str(text) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(Element {
+                    name: name.local_name,
+                    attrs: attributes
+                        .into_iter()
+                        .map(|attr| (attr.name.local_name, attr.value))
+                        .collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let done = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(done),
+                        None => return Ok(done),
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(elem) = stack.last_mut() {
+                        elem.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        bail!("no root element")
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(String::as_str)
+    }
+
+    /// Parse an attribute, or use the default if it's not there.
+    fn attr_or<T: FromStr>(&self, key: &str, default: T) -> anyhow::Result<T> {
+        match self.attr(key) {
+            Some(text) => text
+                .parse()
+                .map_err(|_| anyhow!("bad {} {:?} on <{}>", key, text, self.name)),
+            None => Ok(default),
+        }
+    }
+
+    /// Parse an attribute that has to be there.
+    fn attr_req<T: FromStr>(&self, key: &str) -> anyhow::Result<T> {
+        let text = self
+            .attr(key)
+            .ok_or_else(|| anyhow!("missing {} on <{}>", key, self.name))?;
+        text.parse()
+            .map_err(|_| anyhow!("bad {} {:?} on <{}>", key, text, self.name))
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+pub fn parse_map(text: &str) -> anyhow::Result<(Tilemap, Vec<TilesetSource>)> {
+    let root = Element::parse(text)?;
+    if root.name != "map" {
+        bail!("expected <map>, found <{}>", root.name);
+    }
+    if root.attr("infinite") == Some("1") {
+        bail!("infinite maps aren't supported");
+    }
+
+    let mut tilesets = Vec::new();
+    for tileset in root.children_named("tileset") {
+        let first_gid = tileset.attr_req("firstgid")?;
+        tilesets.push(match tileset.attr("source") {
+            Some(path) => TilesetSource::External {
+                first_gid,
+                path: path.to_owned(),
+            },
+            None => TilesetSource::Embedded(parse_tileset_elem(tileset, first_gid)?),
+        });
+    }
+
+    let mut layers = Vec::new();
+    parse_layers(&root, Vec2::ZERO, &mut layers)?;
+
+    let map = Tilemap {
+        width: root.attr_req("width")?,
+        height: root.attr_req("height")?,
+        tile_width: root.attr_req("tilewidth")?,
+        tile_height: root.attr_req("tileheight")?,
+        tilesets: Vec::new(),
+        layers,
+        properties: properties(&root)?,
+    };
+    Ok((map, tilesets))
+}
+
+pub fn parse_tileset(text: &str, first_gid: u32) -> anyhow::Result<Tileset> {
+    let root = Element::parse(text)?;
+    if root.name != "tileset" {
+        bail!("expected <tileset>, found <{}>", root.name);
+    }
+    parse_tileset_elem(&root, first_gid)
+}
+
+fn parse_tileset_elem(elem: &Element, first_gid: u32) -> anyhow::Result<Tileset> {
+    let mut tiles = HashMap::new();
+    for tile in elem.children_named("tile") {
+        let mut animation = Vec::new();
+        if let Some(frames) = tile.child("animation") {
+            for frame in frames.children_named("frame") {
+                animation.push(AnimationFrame {
+                    tile_id: frame.attr_req("tileid")?,
+                    secs: frame.attr_req::<u32>("duration")? as f32 / 1000.0,
+                });
+            }
+        }
+        tiles.insert(
+            tile.attr_req("id")?,
+            TileData {
+                properties: properties(tile)?,
+                animation,
+            },
+        );
+    }
+
+    let image = elem
+        .child("image")
+        .and_then(|image| image.attr("source"))
+        .ok_or_else(|| anyhow!("only tilesets with one image are supported"))?;
+
+    Ok(Tileset {
+        first_gid,
+        name: elem.attr_or("name", String::new())?,
+        tile_width: elem.attr_req("tilewidth")?,
+        tile_height: elem.attr_req("tileheight")?,
+        tile_count: elem.attr_req("tilecount")?,
+        columns: elem.attr_req("columns")?,
+        margin: elem.attr_or("margin", 0)?,
+        spacing: elem.attr_or("spacing", 0)?,
+        image: image.to_owned(),
+        texture: None,
+        tiles,
+        properties: properties(elem)?,
+    })
+}
+
+/// Read the layers in `parent` into `out`, flattening groups.
+/// `offset` is the offset of the group they're in.
+fn parse_layers(parent: &Element, offset: Vec2, out: &mut Vec<Layer>) -> anyhow::Result<()> {
+    for elem in parent.children.iter() {
+        let offset = offset + vec2(elem.attr_or("offsetx", 0.0)?, elem.attr_or("offsety", 0.0)?);
+        let kind = match elem.name.as_str() {
+            "layer" => {
+                let data = elem
+                    .child("data")
+                    .ok_or_else(|| anyhow!("tile layers need data"))?;
+                if data.child("chunk").is_some() {
+                    bail!("infinite maps aren't supported");
+                }
+                let tiles = match data.attr("encoding") {
+                    Some(encoding) => {
+                        decode_tiles(&data.text, encoding, data.attr("compression").unwrap_or(""))?
+                    }
+                    // Plain XML, one <tile> per cell
+                    None => data
+                        .children_named("tile")
+                        .map(|tile| tile.attr_or("gid", 0))
+                        .collect::<anyhow::Result<_>>()?,
+                };
+                LayerKind::Tiles(TileLayer::new(
+                    elem.attr_req("width")?,
+                    elem.attr_req("height")?,
+                    tiles,
+                )?)
+            }
+            "objectgroup" => LayerKind::Objects(
+                elem.children_named("object")
+                    .map(parse_object)
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            "group" => {
+                parse_layers(elem, offset, out)?;
+                continue;
+            }
+            // Image layers, and everything that isn't a layer
+            _ => continue,
+        };
+
+        out.push(Layer {
+            name: elem.attr_or("name", String::new())?,
+            visible: elem.attr("visible") != Some("0"),
+            opacity: elem.attr_or("opacity", 1.0)?,
+            offset,
+            properties: properties(elem)?,
+            kind,
+        });
+    }
+    Ok(())
+}
+
+fn parse_object(elem: &Element) -> anyhow::Result<Object> {
+    let points = |child: &Element| -> anyhow::Result<Vec<Vec2>> {
+        child
+            .attr("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|pair| {
+                let (x, y) = pair
+                    .split_once(',')
+                    .ok_or_else(|| anyhow!("bad point {:?}", pair))?;
+                Ok(vec2(x.parse()?, y.parse()?))
+            })
+            .collect()
+    };
+    let shape = if elem.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if elem.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = elem.child("polygon") {
+        ObjectShape::Polygon(points(polygon)?)
+    } else if let Some(polyline) = elem.child("polyline") {
+        ObjectShape::Polyline(points(polyline)?)
+    } else {
+        ObjectShape::Rect
+    };
+
+    // Newer versions of Tiled call `type` `class`
+    let kind = elem
+        .attr("class")
+        .or_else(|| elem.attr("type"))
+        .unwrap_or_default()
+        .to_owned();
+
+    Ok(Object {
+        id: elem.attr_or("id", 0)?,
+        name: elem.attr_or("name", String::new())?,
+        kind,
+        pos: vec2(elem.attr_or("x", 0.0)?, elem.attr_or("y", 0.0)?),
+        size: vec2(elem.attr_or("width", 0.0)?, elem.attr_or("height", 0.0)?),
+        rotation: elem.attr_or("rotation", 0.0)?,
+        gid: elem.attr("gid").map(str::parse).transpose()?,
+        visible: elem.attr("visible") != Some("0"),
+        shape,
+        properties: properties(elem)?,
+    })
+}
+
+fn properties(elem: &Element) -> anyhow::Result<Properties> {
+    let mut out = HashMap::new();
+    if let Some(props) = elem.child("properties") {
+        for prop in props.children_named("property") {
+            let kind = prop.attr("type").unwrap_or("string");
+            if kind == "class" {
+                continue;
+            }
+            // Multiline strings go in the text instead of `value`
+            let value = prop.attr("value").unwrap_or(&prop.text);
+            out.insert(prop.attr_req("name")?, parse_property(kind, value)?);
+        }
+    }
+    Ok(Properties(out))
+}
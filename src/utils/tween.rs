@@ -0,0 +1,470 @@
+//! Tweening and easing.
+//!
+//! An `Animation` is a value that changes over time, like a `Tween` from one value to another.
+//! They're plain functions of time, so they're combined like iterators:
+//!
+//! ```ignore
+//! let pop_in = Tween::new(0.0, 1.0, 0.25, Ease::Out(Curve::Back))
+//!     .then(Tween::hold(1.0, 1.0))
+//!     .delayed(0.5)
+//!     .yoyo()
+//!     .repeat(3);
+//! ```
+//!
+//! Either sample them with a time you keep yourself (like `FrameInfo::time`),
+//! or put them in a `Tweener` and advance it once per update with the `FrameInfo`.
+//! Either way they only move with game time, so they pause and replay properly.
+
+use macroquad::prelude::{Color, Vec2};
+
+use std::f32::consts::{PI, TAU};
+
+use crate::boilerplates::FrameInfo;
+
+/// The shapes of the standard easing curves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Curve {
+    Quad,
+    Cubic,
+    Quart,
+    Quint,
+    Sine,
+    Expo,
+    Circ,
+    /// Pulls back a little before going, or overshoots a little after
+    Back,
+    Elastic,
+    Bounce,
+}
+
+impl Curve {
+    /// The ease-in version of the curve, from (0, 0) to (1, 1).
+    fn ease_in(self, t: f32) -> f32 {
+        match self {
+            Curve::Quad => t * t,
+            Curve::Cubic => t * t * t,
+            Curve::Quart => t * t * t * t,
+            Curve::Quint => t * t * t * t * t,
+            Curve::Sine => 1.0 - (t * PI / 2.0).cos(),
+            Curve::Expo => {
+                if t <= 0.0 {
+                    0.0
+                } else {
+                    2f32.powf(10.0 * t - 10.0)
+                }
+            }
+            Curve::Circ => 1.0 - (1.0 - t * t).max(0.0).sqrt(),
+            Curve::Back => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                C3 * t * t * t - C1 * t * t
+            }
+            Curve::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * TAU / 3.0).sin()
+                }
+            }
+            Curve::Bounce => 1.0 - bounce_out(1.0 - t),
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+/// How a tween speeds up and slows down.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Ease {
+    /// Constant speed
+    Linear,
+    /// Start slow, end fast
+    In(Curve),
+    /// Start fast, end slow
+    Out(Curve),
+    /// Start and end slow
+    InOut(Curve),
+}
+
+impl Ease {
+    /// Map progress from 0.0 to 1.0 onto the curve.
+    ///
+    /// The output is 0.0 at the start and 1.0 at the end, but `Back` and `Elastic`
+    /// go past those in between.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::In(curve) => curve.ease_in(t),
+            Ease::Out(curve) => 1.0 - curve.ease_in(1.0 - t),
+            Ease::InOut(curve) => {
+                if t < 0.5 {
+                    curve.ease_in(t * 2.0) / 2.0
+                } else {
+                    1.0 - curve.ease_in((1.0 - t) * 2.0) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Things that can be smoothly blended between.
+pub trait Tweenable: Clone {
+    /// Blend from `self` at `t = 0.0` to `other` at `t = 1.0`.
+    ///
+    /// `t` can go a little outside that range with some easing curves.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Tweenable for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t as f64
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Tweenable for Color {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Color::new(
+            self.r.lerp(&other.r, t),
+            self.g.lerp(&other.g, t),
+            self.b.lerp(&other.b, t),
+            self.a.lerp(&other.a, t),
+        )
+    }
+}
+
+/// A value that changes over time.
+pub trait Animation {
+    type Value;
+
+    /// How many seconds it takes to play once through.
+    /// This is `f32::INFINITY` for things that go forever.
+    fn duration(&self) -> f32;
+
+    /// The value this many seconds in.
+    /// Times before the start or after the end give the value at the start or the end.
+    fn sample(&self, time: f32) -> Self::Value;
+
+    /// Play `next` once this is done.
+    fn then<B>(self, next: B) -> Sequence<Self, B>
+    where
+        Self: Sized,
+        B: Animation<Value = Self::Value>,
+    {
+        Sequence {
+            first: self,
+            second: next,
+        }
+    }
+
+    /// Play `other` at the same time as this. The value is a tuple of both their values.
+    fn with<B: Animation>(self, other: B) -> Parallel<Self, B>
+    where
+        Self: Sized,
+    {
+        Parallel {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Wait this many seconds before starting, holding at the start value.
+    fn delayed(self, secs: f32) -> Delay<Self>
+    where
+        Self: Sized,
+    {
+        Delay {
+            inner: self,
+            delay: secs.max(0.0),
+        }
+    }
+
+    /// Play forwards, then backwards.
+    fn yoyo(self) -> Yoyo<Self>
+    where
+        Self: Sized,
+    {
+        Yoyo { inner: self }
+    }
+
+    /// Play this many times in a row.
+    fn repeat(self, times: u32) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            times: Some(times),
+        }
+    }
+
+    /// Play over and over forever.
+    fn repeat_forever(self) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            times: None,
+        }
+    }
+}
+
+/// Going from one value to another.
+#[derive(Debug, Clone)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    /// How many seconds it takes
+    pub secs: f32,
+    pub ease: Ease,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, secs: f32, ease: Ease) -> Self {
+        Self {
+            from,
+            to,
+            secs: secs.max(0.0),
+            ease,
+        }
+    }
+
+    /// Stay at one value for this many seconds. Handy for pauses in the middle of a sequence.
+    pub fn hold(value: T, secs: f32) -> Self {
+        Self::new(value.clone(), value, secs, Ease::Linear)
+    }
+}
+
+impl<T: Tweenable> Animation for Tween<T> {
+    type Value = T;
+
+    fn duration(&self) -> f32 {
+        self.secs
+    }
+
+    fn sample(&self, time: f32) -> T {
+        let t = if self.secs <= 0.0 {
+            1.0
+        } else {
+            time / self.secs
+        };
+        self.from.lerp(&self.to, self.ease.apply(t))
+    }
+}
+
+/// Two animations one after the other. Make with `Animation::then`.
+#[derive(Debug, Clone)]
+pub struct Sequence<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Animation, B: Animation<Value = A::Value>> Animation for Sequence<A, B> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        self.first.duration() + self.second.duration()
+    }
+
+    fn sample(&self, time: f32) -> A::Value {
+        let split = self.first.duration();
+        if time < split {
+            self.first.sample(time)
+        } else {
+            self.second.sample(time - split)
+        }
+    }
+}
+
+/// Two animations at the same time. Make with `Animation::with`.
+#[derive(Debug, Clone)]
+pub struct Parallel<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Animation, B: Animation> Animation for Parallel<A, B> {
+    type Value = (A::Value, B::Value);
+
+    fn duration(&self) -> f32 {
+        self.first.duration().max(self.second.duration())
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        (self.first.sample(time), self.second.sample(time))
+    }
+}
+
+/// An animation that waits before it starts. Make with `Animation::delayed`.
+#[derive(Debug, Clone)]
+pub struct Delay<A> {
+    inner: A,
+    delay: f32,
+}
+
+impl<A: Animation> Animation for Delay<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        self.delay + self.inner.duration()
+    }
+
+    fn sample(&self, time: f32) -> A::Value {
+        self.inner.sample((time - self.delay).max(0.0))
+    }
+}
+
+/// An animation played forwards then backwards. Make with `Animation::yoyo`.
+#[derive(Debug, Clone)]
+pub struct Yoyo<A> {
+    inner: A,
+}
+
+impl<A: Animation> Animation for Yoyo<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        self.inner.duration() * 2.0
+    }
+
+    fn sample(&self, time: f32) -> A::Value {
+        let half = self.inner.duration();
+        if time <= half {
+            self.inner.sample(time)
+        } else {
+            self.inner.sample((half * 2.0 - time).max(0.0))
+        }
+    }
+}
+
+/// An animation played several times. Make with `Animation::repeat` or `repeat_forever`.
+#[derive(Debug, Clone)]
+pub struct Repeat<A> {
+    inner: A,
+    /// `None` for forever
+    times: Option<u32>,
+}
+
+impl<A: Animation> Animation for Repeat<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        match self.times {
+            Some(times) => self.inner.duration() * times as f32,
+            None => f32::INFINITY,
+        }
+    }
+
+    fn sample(&self, time: f32) -> A::Value {
+        let once = self.inner.duration();
+        if once <= 0.0 || time <= 0.0 {
+            self.inner.sample(time)
+        } else if time >= self.duration() {
+            self.inner.sample(once)
+        } else {
+            self.inner.sample(time % once)
+        }
+    }
+}
+
+/// Plays an animation, keeping track of the time.
+///
+/// Call `update` once per tick, then read the `value`.
+#[derive(Debug, Clone)]
+pub struct Tweener<A> {
+    anim: A,
+    elapsed: f32,
+    just_finished: bool,
+}
+
+impl<A: Animation> Tweener<A> {
+    pub fn new(anim: A) -> Self {
+        Self {
+            anim,
+            elapsed: 0.0,
+            just_finished: false,
+        }
+    }
+
+    /// Advance by the game time that passed this tick.
+    /// Returns if the animation finished this tick.
+    pub fn update(&mut self, frame_info: FrameInfo) -> bool {
+        self.advance(frame_info.dt)
+    }
+
+    /// Advance by this many seconds. Returns if the animation finished just now.
+    pub fn advance(&mut self, secs: f32) -> bool {
+        let was_finished = self.finished();
+        self.elapsed += secs;
+        self.just_finished = !was_finished && self.finished();
+        self.just_finished
+    }
+
+    pub fn value(&self) -> A::Value {
+        self.anim.sample(self.elapsed)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.anim.duration()
+    }
+
+    /// Did the animation finish during the last `update`?
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// How far through the animation this is, from 0.0 to 1.0.
+    /// Always 0.0 for animations that go forever.
+    pub fn progress(&self) -> f32 {
+        let duration = self.anim.duration();
+        if duration.is_infinite() {
+            0.0
+        } else if duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / duration).min(1.0)
+        }
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Start over from the beginning.
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.just_finished = false;
+    }
+
+    pub fn animation(&self) -> &A {
+        &self.anim
+    }
+}